@@ -0,0 +1,75 @@
+// spi_prepared_transfer.rs - Compares the average and worst-case time per
+// transfer of transfer_segments() and transfer_prepared() by repeatedly reading
+// a sample from an MCP3008 ADC using SPI.
+//
+// A Segment already has the layout spidev expects, and transfer_segments()
+// doesn't allocate any memory for small transfers, so the average time per
+// sample is expected to be roughly the same for both methods. The benefit of a
+// prepared transfer is that its memory can be locked into RAM, which avoids page
+// faults during a transfer, and can reduce the worst-case time per sample on a
+// system that's under memory pressure.
+//
+// The MCP3008 doesn't need to be connected to measure the difference in
+// overhead, but the samples will be meaningless without it.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use rppal::spi::{Bus, Mode, PreparedTransfer, Segment, SlaveSelect, Spi};
+
+// Number of samples read for each method.
+const SAMPLES: u32 = 10_000;
+
+// Start bit, followed by the single-ended mode bit and channel 0.
+const READ_CH0: [u8; 3] = [0x01, 0x80, 0x00];
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // The MCP3008 supports clock speeds of up to 3.6 MHz at 5 V, and samples
+    // on the rising edge of the clock signal (SPI mode 0).
+    let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 3_600_000, Mode::Mode0)?;
+
+    // Rebuild the segments for every sample, like a typical sampling loop.
+    let mut sample = 0;
+    let mut max = Duration::default();
+    let start = Instant::now();
+    for _ in 0..SAMPLES {
+        let sample_start = Instant::now();
+        let mut buffer = [0u8; 3];
+        spi.transfer_segments(&[Segment::new(&mut buffer, &READ_CH0)])?;
+        sample = ((u16::from(buffer[1]) & 0x03) << 8) | u16::from(buffer[2]);
+        max = max.max(sample_start.elapsed());
+    }
+    report("transfer_segments()", start.elapsed(), max, sample);
+
+    // Prepare the transfer once, lock its memory into RAM, and reuse it for
+    // every sample.
+    let mut buffer = [0u8; 3];
+    let mut prepared = PreparedTransfer::new(&[Segment::new(&mut buffer, &READ_CH0)]);
+    if let Err(e) = prepared.lock_memory() {
+        println!("Unable to lock memory: {}", e);
+    }
+
+    let mut max = Duration::default();
+    let start = Instant::now();
+    for _ in 0..SAMPLES {
+        let sample_start = Instant::now();
+        spi.transfer_prepared(&mut prepared)?;
+        if let Some(buffer) = prepared.read_buffer(0) {
+            sample = ((u16::from(buffer[1]) & 0x03) << 8) | u16::from(buffer[2]);
+        }
+        max = max.max(sample_start.elapsed());
+    }
+    report("transfer_prepared()", start.elapsed(), max, sample);
+
+    Ok(())
+}
+
+fn report(method: &str, elapsed: Duration, max: Duration, sample: u16) {
+    println!(
+        "{:<20} {:>8.2} µs per sample, {:>8.2} µs worst case, last sample: {}",
+        method,
+        elapsed.as_secs_f64() * 1_000_000.0 / f64::from(SAMPLES),
+        max.as_secs_f64() * 1_000_000.0,
+        sample
+    );
+}
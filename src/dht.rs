@@ -0,0 +1,310 @@
+//! Interface for DHT11 and DHT22 temperature and humidity sensors.
+//!
+//! DHT sensors use a proprietary single-wire protocol, which RPPAL implements
+//! by switching an [`IoPin`] between [`Mode::Output`] and [`Mode::Input`],
+//! and busy-polling the pin's logic level to measure the duration of each
+//! pulse.
+//!
+//! The data line requires a pull-up resistor. The built-in pull-up resistor
+//! is enabled automatically, but most sensor modules include an external
+//! 4.7-10 kΩ resistor as well.
+//!
+//! ## Timing
+//!
+//! Each bit in the 40-bit response frame is encoded in the length of a 26-70 µs
+//! pulse, which means a transfer can get corrupted when the thread is preempted
+//! while reading the response. Every frame is validated with its checksum, and
+//! [`read`] automatically retries failed reads. The sensors can't be read more
+//! often than once per second (DHT11) or once every two seconds (DHT22), so each
+//! retry waits until the sensor is ready for a new measurement, followed by an
+//! additional delay that increases with every retry.
+//!
+//! [`IoPin`]: ../gpio/struct.IoPin.html
+//! [`Mode::Output`]: ../gpio/enum.Mode.html#variant.Output
+//! [`Mode::Input`]: ../gpio/enum.Mode.html#variant.Input
+//! [`read`]: struct.Dht.html#method.read
+
+use std::error;
+use std::fmt;
+use std::result;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::gpio::{Bias, IoPin, Level, Mode};
+
+// Maximum durations in microseconds (µs) for each phase of the response
+const RESPONSE_TIMEOUT: u64 = 200;
+const PULSE_TIMEOUT: u64 = 100;
+
+const FRAME_BITS: usize = 40;
+
+// Delay in milliseconds (ms) before the first retry, doubled for every subsequent retry
+const RETRY_BACKOFF: u64 = 100;
+
+/// Errors that can occur when reading a DHT sensor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Error {
+    /// No response.
+    ///
+    /// The sensor didn't acknowledge the start signal. Make sure the sensor is
+    /// powered, connected to the correct pin, and the correct [`Model`] is selected.
+    ///
+    /// [`Model`]: enum.Model.html
+    NoResponse,
+    /// Timed out.
+    ///
+    /// The sensor stopped responding while transferring the response frame.
+    TimedOut,
+    /// Checksum mismatch.
+    ///
+    /// The checksum of the response frame doesn't match. The data was most
+    /// likely corrupted during the transfer.
+    InvalidChecksum,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::NoResponse => write!(f, "No response"),
+            Error::TimedOut => write!(f, "Timed out"),
+            Error::InvalidChecksum => write!(f, "Checksum mismatch"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Result type returned from methods that can have `dht::Error`s.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Supported sensor models.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Model {
+    /// DHT11.
+    Dht11,
+    /// DHT22, AM2302, DHT21 and AM2301.
+    Dht22,
+}
+
+impl Model {
+    // Duration of the start signal
+    fn start_signal(self) -> Duration {
+        match self {
+            Model::Dht11 => Duration::from_millis(18),
+            Model::Dht22 => Duration::from_micros(1100),
+        }
+    }
+
+    // Minimum interval between two measurements
+    fn sampling_interval(self) -> Duration {
+        match self {
+            Model::Dht11 => Duration::from_secs(1),
+            Model::Dht22 => Duration::from_secs(2),
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Model::Dht11 => write!(f, "DHT11"),
+            Model::Dht22 => write!(f, "DHT22"),
+        }
+    }
+}
+
+/// Temperature and humidity measurement.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Reading {
+    /// Temperature in degrees Celsius (°C).
+    pub temperature: f32,
+    /// Relative humidity in percent (%).
+    pub humidity: f32,
+}
+
+/// Provides access to a DHT11 or DHT22 sensor.
+///
+/// More information on the timing limitations can be found [here].
+///
+/// [here]: index.html
+#[derive(Debug)]
+pub struct Dht {
+    pin: IoPin,
+    model: Model,
+    retries: u32,
+    last_read: Option<Instant>,
+}
+
+impl Dht {
+    /// Constructs a new `Dht`.
+    ///
+    /// `pin` specifies the data line. The pin's built-in pull-up resistor is
+    /// enabled, and the pin is released.
+    ///
+    /// By default, `retries` is set to `3`.
+    pub fn new(mut pin: IoPin, model: Model) -> Dht {
+        // Preset the output state, so switching the mode to output pulls the line low
+        pin.set_mode(Mode::Input);
+        pin.set_bias(Bias::PullUp);
+        pin.set_low();
+
+        Dht {
+            pin,
+            model,
+            retries: 3,
+            last_read: None,
+        }
+    }
+
+    /// Returns the sensor model.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns the number of times a failed read is retried.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Sets the number of times a failed read is retried.
+    ///
+    /// By default, `retries` is set to `3`.
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Reads the current temperature and relative humidity.
+    ///
+    /// If the sensor doesn't respond, or the response frame is corrupted,
+    /// `read` retries up to [`retries`] times before returning the last error.
+    /// If the sensor isn't ready for a new measurement, `read` blocks until the
+    /// sensor's minimum sampling interval has elapsed. Each retry adds a delay
+    /// that starts at 100 ms, and doubles for every subsequent retry.
+    ///
+    /// [`retries`]: #method.set_retries
+    pub fn read(&mut self) -> Result<Reading> {
+        let mut attempt = 0;
+
+        loop {
+            self.wait_until_ready();
+
+            match self.read_frame() {
+                Ok(frame) => return Ok(self.decode(&frame)),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(_) => {
+                    // Back off, in case the sensor needs more time to recover
+                    thread::sleep(Duration::from_millis(RETRY_BACKOFF << attempt.min(6)));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    // Blocks until the minimum sampling interval has elapsed since the last read
+    fn wait_until_ready(&self) {
+        if let Some(last_read) = self.last_read {
+            let elapsed = last_read.elapsed();
+            let interval = self.model.sampling_interval();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> Result<[u8; 5]> {
+        self.last_read = Some(Instant::now());
+
+        // Start signal
+        self.pin.set_mode(Mode::Output);
+        thread::sleep(self.model.start_signal());
+        self.pin.set_mode(Mode::Input);
+
+        // Wait for the pull-up resistor to release the line, so the start signal
+        // isn't mistaken for the response
+        self.wait_for(Level::High, PULSE_TIMEOUT)
+            .ok_or(Error::NoResponse)?;
+
+        // Response signal, low for 80 µs followed by high for 80 µs
+        self.wait_for(Level::Low, RESPONSE_TIMEOUT)
+            .ok_or(Error::NoResponse)?;
+        self.wait_for(Level::High, PULSE_TIMEOUT)
+            .ok_or(Error::NoResponse)?;
+        self.wait_for(Level::Low, PULSE_TIMEOUT)
+            .ok_or(Error::NoResponse)?;
+
+        // Each bit starts with a 50 µs low pulse, followed by a 26-28 µs (0) or 70 µs (1)
+        // high pulse. Comparing both pulses avoids relying on absolute timing.
+        let mut frame = [0u8; 5];
+        for bit in 0..FRAME_BITS {
+            let low = self
+                .wait_for(Level::High, PULSE_TIMEOUT)
+                .ok_or(Error::TimedOut)?;
+            let high = self
+                .wait_for(Level::Low, PULSE_TIMEOUT)
+                .ok_or(Error::TimedOut)?;
+
+            if high > low {
+                frame[bit / 8] |= 0x80 >> (bit % 8);
+            }
+        }
+
+        let checksum = frame[..4]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        if checksum != frame[4] {
+            return Err(Error::InvalidChecksum);
+        }
+
+        Ok(frame)
+    }
+
+    fn decode(&self, frame: &[u8; 5]) -> Reading {
+        match self.model {
+            Model::Dht11 => {
+                let humidity = f32::from(frame[0]) + f32::from(frame[1]) * 0.1;
+                let temperature = f32::from(frame[2]) + f32::from(frame[3] & 0x7F) * 0.1;
+
+                Reading {
+                    temperature: if frame[3] & 0x80 != 0 {
+                        -temperature
+                    } else {
+                        temperature
+                    },
+                    humidity,
+                }
+            }
+            Model::Dht22 => {
+                let humidity = f32::from(u16::from_be_bytes([frame[0], frame[1]])) / 10.0;
+                let temperature = f32::from(u16::from_be_bytes([frame[2] & 0x7F, frame[3]])) / 10.0;
+
+                Reading {
+                    temperature: if frame[2] & 0x80 != 0 {
+                        -temperature
+                    } else {
+                        temperature
+                    },
+                    humidity,
+                }
+            }
+        }
+    }
+
+    // Busy-waits until the pin changes to the specified level, and returns the
+    // elapsed time, or None if the level didn't change within timeout µs
+    #[inline(always)]
+    fn wait_for(&self, level: Level, timeout: u64) -> Option<Duration> {
+        let start = Instant::now();
+        let timeout = Duration::from_micros(timeout);
+
+        loop {
+            let elapsed = start.elapsed();
+            if self.pin.read() == level {
+                return Some(elapsed);
+            }
+
+            if elapsed > timeout {
+                return None;
+            }
+        }
+    }
+}
@@ -61,6 +61,13 @@
 //! Some of their functionality can be moved to different pins. Read
 //! `/boot/overlays/README` for more information.
 //!
+//! ### Bus IDs
+//!
+//! Bus IDs can differ between Raspberry Pi models, and may change depending on
+//! the order in which device tree overlays are loaded. Instead of selecting a bus
+//! by its ID, [`with_node`] selects the bus bound to a specific device tree node,
+//! and [`with_pins`] selects the bus that's routed to a specific pair of GPIO pins.
+//!
 //! ### Software I2C
 //!
 //! In addition to the hardware I2C buses, it's possible to configure a
@@ -68,6 +75,11 @@
 //! device tree overlay. More details on enabling and configuring `i2c-gpio`
 //! can be found in `/boot/overlays/README`.
 //!
+//! Alternatively, [`SoftI2c`] offers a bit-banged software I2C bus implemented
+//! in RPPAL itself, which doesn't require any changes to your configuration. Besides
+//! the pins, `SoftI2c` can be configured at runtime, and supports clock stretching,
+//! 10-bit slave addresses and bus recovery.
+//!
 //! ## Transmission speed
 //!
 //! The BSC supports I2C data transfer rates up to 400 kbit/s (Fast-mode).
@@ -79,6 +91,16 @@
 //! clock frequency in hertz (Hz). Remember to reboot
 //! the Raspberry Pi afterwards.
 //!
+//! ## Slave mode
+//!
+//! Depending on the Raspberry Pi model and the bus driver, the Raspberry Pi can act
+//! as an I2C slave device, responding to an external bus master. [`Target`]
+//! instantiates the kernel's `i2c-slave-eeprom` backend, which emulates an EEPROM
+//! with a memory window that's shared between the bus master and your application.
+//! [`Capabilities::slave`] indicates whether the bus driver supports slave mode.
+//!
+//! Instantiating a slave backend requires root privileges.
+//!
 //! ## Not supported
 //!
 //! Some I2C and SMBus features aren't fully supported by the `i2cdev` interface, the underlying driver or
@@ -91,7 +113,8 @@
 //! delay is longer than half of a clock period. More information can be found [here](https://elinux.org/BCM2835_datasheet_errata#p35_I2C_clock_stretching).
 //!
 //! A possible workaround for slave devices that require clock stretching at other points during the transfer is
-//! to use a bit-banged software I2C bus by configuring the `i2c-gpio` device tree overlay as described in `/boot/overlays/README`.
+//! to use a bit-banged software I2C bus by configuring the `i2c-gpio` device tree overlay as described in `/boot/overlays/README`,
+//! or by using [`SoftI2c`].
 //!
 //! ## Troubleshooting
 //!
@@ -103,29 +126,65 @@
 //!
 //! ### Timed out
 //!
-//! Transactions return an `io::ErrorKind::TimedOut` error when their duration
-//! exceeds the timeout value. You can change the timeout using [`set_timeout`].
+//! Transactions return `Err(`[`Error::Timeout`]`)` when their duration
+//! exceeds the timeout value. You can change the timeout using [`set_timeout`],
+//! or at construction through [`I2cConfig`].
+//!
+//! ### Transient errors
+//!
+//! Slave devices that are temporarily busy may not acknowledge their address, and
+//! transfers on a bus with multiple masters can lose arbitration. [`I2cConfig`]
+//! configures how often those transfers are automatically retried. The total number
+//! of retries is available through [`retry_count`].
+//!
+//! ### Stuck bus
+//!
+//! If a slave device was interrupted in the middle of a transfer, for instance
+//! because the Raspberry Pi was reset, it may keep SDA low indefinitely, causing
+//! every transaction to fail. Call [`recover_bus`] to send the clock pulses the
+//! slave device is waiting for, and release the bus.
 //!
 //! [`new`]: struct.I2c.html#method.new
 //! [`with_bus`]: struct.I2c.html#method.with_bus
+//! [`with_node`]: struct.I2c.html#method.with_node
+//! [`with_pins`]: struct.I2c.html#method.with_pins
 //! [`set_timeout`]: struct.I2c.html#method.set_timeout
+//! [`I2cConfig`]: struct.I2cConfig.html
+//! [`Error::Timeout`]: enum.Error.html#variant.Timeout
+//! [`retry_count`]: struct.I2c.html#method.retry_count
+//! [`recover_bus`]: struct.I2c.html#method.recover_bus
+//! [`SoftI2c`]: struct.SoftI2c.html
+//! [`Target`]: struct.Target.html
+//! [`Capabilities::slave`]: struct.Capabilities.html#method.slave
 
 #![allow(dead_code)]
 
+use std::cell::Cell;
 use std::error;
 use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::io::AsRawFd;
+use std::path::Path;
 use std::result;
+use std::thread;
+use std::time::Duration;
 
 use libc::c_ulong;
 
+use crate::gpio::{self, Gpio};
 use crate::system;
-use crate::system::{DeviceInfo, Model};
+use crate::system::{devicetree, DeviceInfo, Model};
+use crate::trace::{Direction, Interface, TraceStart, Tracer};
 
+#[cfg(feature = "embedded-hal-async")]
+mod async_i2c;
+mod bus;
+mod config;
 #[cfg(any(
     feature = "embedded-hal-0",
     feature = "embedded-hal",
@@ -133,13 +192,32 @@ use crate::system::{DeviceInfo, Model};
 ))]
 mod hal;
 mod ioctl;
-
+mod message;
+mod soft_i2c;
+mod target;
+
+#[cfg(feature = "embedded-hal-async")]
+pub use self::async_i2c::AsyncI2c;
+pub use self::bus::{I2cBus, I2cDevice};
+pub use self::config::{I2cConfig, RetryOn};
 pub use self::ioctl::Capabilities;
+pub use self::message::I2cMessage;
+pub use self::soft_i2c::SoftI2c;
+pub use self::target::{Target, TargetMemory};
+
+// SMBus Alert Response Address
+const ALERT_RESPONSE_ADDRESS: u16 = 0x0C;
+
+// Clock frequency used during bus recovery (Standard-mode)
+const RECOVERY_CLOCK_SPEED: u32 = 100_000;
 
 /// Errors that can occur when accessing the I2C peripheral.
 #[derive(Debug)]
 pub enum Error {
     /// I/O error.
+    ///
+    /// Errors that can be identified based on their error code are
+    /// reported through the more specific variants instead.
     Io(io::Error),
     /// Invalid slave address.
     ///
@@ -161,6 +239,39 @@ pub enum Error {
     /// doesn't provide any of the common user-accessible system files
     /// that are used to identify the model and SoC.
     UnknownModel,
+    /// Packet Error Code mismatch.
+    ///
+    /// SMBus Packet Error Checking is enabled, and the PEC byte received from
+    /// the slave device doesn't match the data. The data was most likely
+    /// corrupted during the transfer.
+    InvalidPec,
+    /// The slave device didn't acknowledge its address or the transferred data.
+    ///
+    /// The included [`NoAcknowledgeSource`] indicates which part of the transfer
+    /// wasn't acknowledged, if the underlying driver reports it.
+    ///
+    /// [`NoAcknowledgeSource`]: enum.NoAcknowledgeSource.html
+    NoAcknowledge(NoAcknowledgeSource),
+    /// Arbitration was lost to another bus master.
+    ///
+    /// Another bus master started a transfer at the same time. Retrying the
+    /// transfer once the bus is idle is typically safe.
+    ArbitrationLoss,
+    /// Transfer timed out.
+    ///
+    /// The transfer took longer than the configured timeout, which usually means a
+    /// slave device is stretching the clock indefinitely.
+    Timeout,
+    /// Bus error.
+    ///
+    /// The underlying driver detected a protocol violation or an unexpected
+    /// bus condition, such as a line that's stuck low.
+    BusError,
+    /// GPIO error.
+    ///
+    /// Bus recovery temporarily reconfigures the SDA and SCL pins through
+    /// the GPIO peripheral, which failed with the included error.
+    Gpio(gpio::Error),
 }
 
 impl fmt::Display for Error {
@@ -170,6 +281,12 @@ impl fmt::Display for Error {
             Error::InvalidSlaveAddress(address) => write!(f, "Invalid slave address: {}", address),
             Error::FeatureNotSupported => write!(f, "I2C/SMBus feature not supported"),
             Error::UnknownModel => write!(f, "Unknown Raspberry Pi model"),
+            Error::InvalidPec => write!(f, "Packet Error Code mismatch"),
+            Error::NoAcknowledge(source) => write!(f, "No acknowledgement: {}", source),
+            Error::ArbitrationLoss => write!(f, "Arbitration lost"),
+            Error::Timeout => write!(f, "Transfer timed out"),
+            Error::BusError => write!(f, "Bus error"),
+            Error::Gpio(ref err) => write!(f, "GPIO error: {}", err),
         }
     }
 }
@@ -178,7 +295,19 @@ impl error::Error for Error {}
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
-        Error::Io(err)
+        // Error codes as listed in the kernel's Documentation/i2c/fault-codes.rst.
+        // Depending on the driver, a NACK is reported as ENXIO (address) or
+        // EREMOTEIO (address or data).
+        match err.raw_os_error() {
+            Some(libc::EBADMSG) => Error::InvalidPec,
+            Some(libc::ENXIO) => Error::NoAcknowledge(NoAcknowledgeSource::Address),
+            Some(libc::EREMOTEIO) => Error::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Some(libc::EAGAIN) => Error::ArbitrationLoss,
+            Some(libc::ETIMEDOUT) => Error::Timeout,
+            Some(libc::EIO) | Some(libc::EPROTO) => Error::BusError,
+            Some(libc::EOPNOTSUPP) => Error::FeatureNotSupported,
+            _ => Error::Io(err),
+        }
     }
 }
 
@@ -188,9 +317,68 @@ impl From<system::Error> for Error {
     }
 }
 
+impl From<gpio::Error> for Error {
+    fn from(err: gpio::Error) -> Error {
+        Error::Gpio(err)
+    }
+}
+
+/// Parts of a transfer that can go unacknowledged.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum NoAcknowledgeSource {
+    /// The slave address wasn't acknowledged.
+    Address,
+    /// A data byte wasn't acknowledged.
+    Data,
+    /// The underlying driver doesn't report which part wasn't acknowledged.
+    Unknown,
+}
+
+impl fmt::Display for NoAcknowledgeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            NoAcknowledgeSource::Address => write!(f, "Address"),
+            NoAcknowledgeSource::Data => write!(f, "Data"),
+            NoAcknowledgeSource::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
 /// Result type returned from methods that can have `i2c::Error`s.
 pub type Result<T> = result::Result<T, Error>;
 
+/// Methods used to detect the presence of a slave device.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ProbeMethod {
+    /// Selects the safest available method for each address.
+    ///
+    /// Receive Byte is used for addresses 0x30-0x37 and 0x50-0x5F, where
+    /// a Quick Write could corrupt the contents of an EEPROM. Quick Write is used
+    /// for all other addresses. If a method isn't supported by the underlying
+    /// drivers, the other method is used instead.
+    Auto,
+    /// SMBus Quick Command, with the R/W bit set to write.
+    ///
+    /// A Quick Write is known to corrupt the contents of some EEPROMs, and to
+    /// lock up some write-only devices.
+    QuickWrite,
+    /// SMBus Receive Byte.
+    ///
+    /// Some write-only devices can't be detected with Receive Byte, and
+    /// reading from some devices may change their state.
+    ReceiveByte,
+}
+
+impl fmt::Display for ProbeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ProbeMethod::Auto => write!(f, "Auto"),
+            ProbeMethod::QuickWrite => write!(f, "QuickWrite"),
+            ProbeMethod::ReceiveByte => write!(f, "ReceiveByte"),
+        }
+    }
+}
+
 /// Provides access to the Raspberry Pi's I2C peripheral.
 ///
 /// Before using `I2c`, make sure your Raspberry Pi has the necessary I2C buses
@@ -214,6 +402,10 @@ pub struct I2c {
     i2cdev: File,
     addr_10bit: bool,
     address: u16,
+    pec: Cell<bool>,
+    config: Cell<I2cConfig>,
+    retry_count: Cell<u64>,
+    tracer: Option<Tracer>,
     // The not_sync field is a workaround to force !Sync. I2c isn't safe for
     // Sync because of ioctl() and the underlying drivers. This avoids needing
     // #![feature(optin_builtin_traits)] to manually add impl !Sync for I2c.
@@ -252,12 +444,74 @@ impl I2c {
     ///
     /// [here]: index.html#i2c-buses
     pub fn with_bus(bus: u8) -> Result<I2c> {
-        // bus is a u8, because any 8-bit bus ID could potentially
-        // be configured for bit banging I2C using i2c-gpio.
-        let i2cdev = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(format!("/dev/i2c-{}", bus))?;
+        I2c::open(format!("/dev/i2c-{}", bus), I2cConfig::default())
+    }
+
+    /// Constructs a new `I2c` using the I2C character device specified by `path`.
+    ///
+    /// `with_path` can be used with device paths that don't follow the
+    /// `/dev/i2c-N` naming scheme, such as symlinks created by udev rules. The bus
+    /// ID is retrieved from the character device itself.
+    pub fn with_path<P: AsRef<Path>>(path: P) -> Result<I2c> {
+        I2c::open(path, I2cConfig::default())
+    }
+
+    /// Constructs a new `I2c` using the bus that's bound to the specified device
+    /// tree `node`.
+    ///
+    /// `node` contains either the node name including its unit address, for
+    /// instance `i2c@7e804000`, or its full path, for instance
+    /// `soc/i2c@7e804000`. Compared to [`with_bus`], `with_node` isn't affected
+    /// by bus IDs that change between Raspberry Pi models, or depending on the
+    /// order in which device tree overlays are loaded.
+    ///
+    /// Returns an `io::ErrorKind::NotFound` error if none of the I2C buses match.
+    ///
+    /// [`with_bus`]: #method.with_bus
+    pub fn with_node(node: &str) -> Result<I2c> {
+        I2c::with_bus(find_bus(|path| devicetree::node_matches(path, node))?)
+    }
+
+    /// Constructs a new `I2c` using the bus that's routed to the specified
+    /// SDA and SCL pins.
+    ///
+    /// `sda` and `scl` contain BCM GPIO pin numbers. The pins are matched against
+    /// the pin configuration specified by each bus's device tree node.
+    ///
+    /// Returns an `io::ErrorKind::NotFound` error if none of the I2C buses match.
+    pub fn with_pins(sda: u8, scl: u8) -> Result<I2c> {
+        I2c::with_bus(find_bus(|path| {
+            devicetree::node_pins(path)
+                .map(|pins| pins.contains(&sda) && pins.contains(&scl))
+                .unwrap_or(false)
+        })?)
+    }
+
+    /// Constructs a new `I2c` using the specified bus and configuration.
+    ///
+    /// `with_config` works similarly to [`with_bus`], but also applies the
+    /// timeout and retry settings contained in `config`.
+    ///
+    /// [`with_bus`]: #method.with_bus
+    pub fn with_config(bus: u8, config: I2cConfig) -> Result<I2c> {
+        let i2c = I2c::open(format!("/dev/i2c-{}", bus), config)?;
+        i2c.apply_timeout(config.timeout)?;
+
+        Ok(i2c)
+    }
+
+    fn open<P: AsRef<Path>>(path: P, config: I2cConfig) -> Result<I2c> {
+        let i2cdev = OpenOptions::new().read(true).write(true).open(path)?;
+
+        // The i2cdev minor number matches the bus ID. bus is a u8, because any
+        // 8-bit bus ID could potentially be configured for bit banging I2C using i2c-gpio.
+        let rdev = i2cdev.metadata()?.rdev();
+        let bus = u8::try_from((rdev & 0xff) | ((rdev >> 12) & 0xf_ff00)).map_err(|_| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bus ID out of range",
+            ))
+        })?;
 
         let capabilities = ioctl::funcs(i2cdev.as_raw_fd())?;
 
@@ -277,6 +531,10 @@ impl I2c {
             i2cdev,
             addr_10bit: false,
             address: 0,
+            pec: Cell::new(false),
+            config: Cell::new(config),
+            retry_count: Cell::new(0),
+            tracer: None,
             not_sync: PhantomData,
         })
     }
@@ -296,6 +554,26 @@ impl I2c {
         self.bus
     }
 
+    /// Returns the current timeout and retry configuration.
+    pub fn config(&self) -> I2cConfig {
+        self.config.get()
+    }
+
+    /// Returns how many times a failed transfer has been retried since
+    /// construction, or since the last call to [`reset_retry_count`].
+    ///
+    /// [`reset_retry_count`]: #method.reset_retry_count
+    pub fn retry_count(&self) -> u64 {
+        self.retry_count.get()
+    }
+
+    /// Resets the counter returned by [`retry_count`] to zero.
+    ///
+    /// [`retry_count`]: #method.retry_count
+    pub fn reset_retry_count(&self) {
+        self.retry_count.set(0);
+    }
+
     /// Returns the clock frequency in hertz (Hz).
     pub fn clock_speed(&self) -> Result<u32> {
         let mut buffer = [0u8; 4];
@@ -326,9 +604,7 @@ impl I2c {
     /// [`set_addr_10bit`]: #method.set_addr_10bit
     pub fn set_slave_address(&mut self, slave_address: u16) -> Result<()> {
         // Filter out invalid and unsupported addresses
-        if (!self.addr_10bit && ((slave_address >> 3) == 0b1111 || slave_address > 0x7F))
-            || (self.addr_10bit && slave_address > 0x03FF)
-        {
+        if !is_valid_address(slave_address, self.addr_10bit) {
             return Err(Error::InvalidSlaveAddress(slave_address));
         }
 
@@ -341,25 +617,305 @@ impl I2c {
 
     /// Sets the maximum duration of a transaction in milliseconds (ms).
     ///
-    /// Transactions that take longer than `timeout` return an
-    /// `io::ErrorKind::TimedOut` error.
+    /// Transactions that take longer than `timeout` return
+    /// `Err(`[`Error::Timeout`]`)`.
+    ///
+    /// `timeout` has a resolution of 10ms, and is rounded up to the nearest
+    /// multiple of 10ms. The timeout can also be set at
+    /// construction through [`I2cConfig`].
     ///
-    /// `timeout` has a resolution of 10ms.
+    /// [`Error::Timeout`]: enum.Error.html#variant.Timeout
+    /// [`I2cConfig`]: struct.I2cConfig.html
     pub fn set_timeout(&self, timeout: u32) -> Result<()> {
+        self.apply_timeout(Duration::from_millis(u64::from(timeout)))
+    }
+
+    fn apply_timeout(&self, timeout: Duration) -> Result<()> {
         // Contrary to the i2cdev documentation, this seems to
         // be used as a timeout for (part of?) the I2C transaction.
-        ioctl::set_timeout(self.i2cdev.as_raw_fd(), timeout as c_ulong)?;
+        let millis = timeout.as_millis().min(u128::from(u64::MAX)) as u64;
+        let applied = ioctl::set_timeout(self.i2cdev.as_raw_fd(), millis)?;
+
+        // Store the timeout that was actually applied
+        let mut config = self.config.get();
+        config.timeout = Duration::from_millis(applied);
+        self.config.set(config);
+
+        Ok(())
+    }
+
+    /// Attempts to release a slave device that's holding SDA low.
+    ///
+    /// A slave device can get stuck in the middle of a transfer when the Raspberry Pi
+    /// is reset, and will keep SDA low while it's waiting for more clock pulses. Until
+    /// SDA is released, every transaction on the bus fails.
+    ///
+    /// `recover_bus` temporarily switches the SDA and SCL pins from their
+    /// alternate function to GPIO mode, sends up to 9 clock pulses until SDA is released,
+    /// followed by a STOP condition, and then restores the pins' original [`Mode`].
+    ///
+    /// The pins are identified based on the Raspberry Pi model and the I2C bus
+    /// ID, and are expected to match the default configuration listed [here].
+    /// `recover_bus` returns `Err(`[`Error::FeatureNotSupported`]`)` for buses
+    /// that aren't listed, and `Err(`[`Error::Gpio`]`)` if the pins can't be
+    /// accessed, for instance because they're already in use elsewhere in your application.
+    ///
+    /// [`Mode`]: ../gpio/enum.Mode.html
+    /// [here]: index.html#i2c-buses
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    /// [`Error::Gpio`]: enum.Error.html#variant.Gpio
+    pub fn recover_bus(&self) -> Result<()> {
+        let (sda, scl) = DeviceInfo::new()?
+            .i2c_pins(self.bus)
+            .ok_or(Error::FeatureNotSupported)?;
+
+        let gpio = Gpio::new()?;
+
+        // IoPin resets the pins to their original mode when they go out of scope. The
+        // pull-up/pull-down resistors are left untouched, since their current state
+        // can't be read back on all models.
+        let sda = gpio.get(sda)?.into_io(gpio::Mode::Input);
+        let scl = gpio.get(scl)?.into_io(gpio::Mode::Input);
+
+        SoftI2c::with_io_pins(sda, scl, RECOVERY_CLOCK_SPEED)?.recover_bus()
+    }
+
+    /// Checks whether a slave device responds at the specified address.
+    ///
+    /// `probe` uses [`ProbeMethod::Auto`] to select the safest available method
+    /// for the address. Addresses that are in use by a kernel driver can't be
+    /// probed, and are reported as present.
+    ///
+    /// The current slave address is restored afterwards. `address` is interpreted as
+    /// a 10-bit address if 10-bit addressing is enabled through [`set_addr_10bit`].
+    ///
+    /// Sequence: START → Address + R/W Bit → STOP
+    ///
+    /// [`ProbeMethod::Auto`]: enum.ProbeMethod.html#variant.Auto
+    /// [`set_addr_10bit`]: #method.set_addr_10bit
+    pub fn probe(&mut self, address: u16) -> Result<bool> {
+        if !is_valid_address(address, self.addr_10bit) {
+            return Err(Error::InvalidSlaveAddress(address));
+        }
+
+        let result = self.probe_address(address, ProbeMethod::Auto);
+
+        // Always try to restore the slave address, but report the original error if there was one
+        let restore = self.restore_slave_address();
+        let present = result?;
+        restore?;
+
+        Ok(present)
+    }
+
+    /// Scans the bus for slave devices, and returns the addresses that responded.
+    ///
+    /// `range` specifies which addresses are probed. Reserved 7-bit addresses
+    /// (0x00-0x07 and 0x78-0x7F) are skipped. If 10-bit addressing is enabled
+    /// through [`set_addr_10bit`], `range` is interpreted as a range of 10-bit addresses.
+    ///
+    /// `method` selects how each address is probed. [`ProbeMethod::Auto`] matches
+    /// the default behavior of `i2cdetect`. If the selected method isn't supported
+    /// by the underlying drivers, `scan` returns `Err(`[`Error::FeatureNotSupported`]`)`.
+    ///
+    /// Addresses that are in use by a kernel driver can't be probed, and are
+    /// reported as present. The current slave address is restored afterwards.
+    ///
+    /// [`set_addr_10bit`]: #method.set_addr_10bit
+    /// [`ProbeMethod::Auto`]: enum.ProbeMethod.html#variant.Auto
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn scan<R: RangeBounds<u16>>(&mut self, range: R, method: ProbeMethod) -> Result<Vec<u16>> {
+        let (min, max) = if self.addr_10bit {
+            (0x0000, 0x03FF)
+        } else {
+            (0x08, 0x77)
+        };
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => min,
+        }
+        .max(min);
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end,
+            Bound::Excluded(&end) => end.saturating_sub(1),
+            Bound::Unbounded => max,
+        }
+        .min(max);
+
+        let mut addresses = Vec::new();
+        let mut result = Ok(());
+
+        for address in start..=end {
+            match self.probe_address(address, method) {
+                Ok(true) => addresses.push(address),
+                Ok(false) => (),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        // Always try to restore the slave address, but report the original error if there was one
+        let restore = self.restore_slave_address();
+        result?;
+        restore?;
+
+        Ok(addresses)
+    }
+
+    fn probe_address(&self, address: u16, method: ProbeMethod) -> Result<bool> {
+        let capabilities = self.capabilities();
+
+        let method = match method {
+            ProbeMethod::Auto => {
+                let preferred =
+                    if (0x30..=0x37).contains(&address) || (0x50..=0x5F).contains(&address) {
+                        ProbeMethod::ReceiveByte
+                    } else {
+                        ProbeMethod::QuickWrite
+                    };
+
+                match preferred {
+                    ProbeMethod::QuickWrite if !capabilities.smbus_quick_command() => {
+                        ProbeMethod::ReceiveByte
+                    }
+                    ProbeMethod::ReceiveByte if !capabilities.smbus_receive_byte() => {
+                        ProbeMethod::QuickWrite
+                    }
+                    _ => preferred,
+                }
+            }
+            _ => method,
+        };
+
+        let supported = match method {
+            ProbeMethod::QuickWrite => capabilities.smbus_quick_command(),
+            _ => capabilities.smbus_receive_byte(),
+        };
+
+        if !supported {
+            return Err(Error::FeatureNotSupported);
+        }
+
+        // Addresses claimed by a kernel driver return EBUSY
+        match ioctl::set_slave_address(self.i2cdev.as_raw_fd(), c_ulong::from(address)) {
+            Err(ref e) if e.raw_os_error() == Some(libc::EBUSY) => return Ok(true),
+            result => result?,
+        }
+
+        let result = match method {
+            ProbeMethod::QuickWrite => ioctl::smbus_quick_command(self.i2cdev.as_raw_fd(), false),
+            _ => ioctl::smbus_receive_byte(self.i2cdev.as_raw_fd()).map(|_| ()),
+        };
+
+        match result {
+            Ok(()) => Ok(true),
+            // The slave address wasn't acknowledged
+            Err(ref e) if matches!(e.raw_os_error(), Some(libc::ENXIO) | Some(libc::EREMOTEIO)) => {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn restore_slave_address(&self) -> Result<()> {
+        ioctl::set_slave_address(self.i2cdev.as_raw_fd(), c_ulong::from(self.address))?;
 
         Ok(())
     }
 
     fn set_retries(&self, retries: u32) -> Result<()> {
         // Set to private. While i2cdev implements retries, the underlying drivers don't.
+        // Retries are handled by I2c::retry instead.
         ioctl::set_retries(self.i2cdev.as_raw_fd(), retries as c_ulong)?;
 
         Ok(())
     }
 
+    // Calls f, and retries it on transient errors based on the current configuration
+    fn retry<T, F>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+    {
+        let config = self.config.get();
+        let mut attempt = 0;
+
+        loop {
+            match f() {
+                Err(ref e) if attempt < config.retries && config.retry_on.matches(e) => {
+                    attempt += 1;
+                    self.retry_count.set(self.retry_count.get().wrapping_add(1));
+
+                    if config.backoff > Duration::default() {
+                        thread::sleep(config.backoff);
+                    }
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    /// Returns the attached [`Tracer`], if any.
+    ///
+    /// [`Tracer`]: ../trace/struct.Tracer.html
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Attaches a [`Tracer`] that records every read, write and transfer,
+    /// including SMBus operations.
+    ///
+    /// Set `tracer` to `None` to disable tracing. More information can be
+    /// found in the [`trace`] module documentation.
+    ///
+    /// By default, tracing is disabled.
+    ///
+    /// [`Tracer`]: ../trace/struct.Tracer.html
+    /// [`trace`]: ../trace/index.html
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    // Returns the start time of an operation, if tracing is enabled
+    fn trace_start(&self) -> Option<TraceStart> {
+        self.tracer.as_ref().map(|_| TraceStart::now())
+    }
+
+    // Records a completed operation, if tracing is enabled. write and read are
+    // only called when the operation is recorded.
+    fn trace<T, W, R>(
+        &self,
+        start: Option<TraceStart>,
+        direction: Direction,
+        write: W,
+        result: &Result<T>,
+        read: R,
+    ) where
+        W: FnOnce() -> Vec<u8>,
+        R: FnOnce(&T) -> Vec<u8>,
+    {
+        if let (Some(tracer), Some(start)) = (&self.tracer, start) {
+            let (read_data, error) = match result {
+                Ok(value) => (read(value), None),
+                Err(e) => (Vec::new(), Some(e.to_string())),
+            };
+
+            tracer.record(start.finish(
+                Interface::I2c,
+                Some(self.bus),
+                Some(self.address),
+                direction,
+                write(),
+                read_data,
+                error,
+            ));
+        }
+    }
+
     /// Enables or disables 10-bit addressing.
     ///
     /// 10-bit addressing currently isn't supported on the Raspberry Pi. `set_addr_10bit` returns
@@ -388,7 +944,13 @@ impl I2c {
     ///
     /// Returns how many bytes were read.
     pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        Ok(self.i2cdev.read(buffer)?)
+        let start = self.trace_start();
+        let result = self.retry(|| (&self.i2cdev).read(buffer));
+        self.trace(start, Direction::Read, Vec::new, &result, |&len| {
+            buffer[..len].to_vec()
+        });
+
+        result
     }
 
     /// Sends the outgoing data contained in `buffer` to the slave device.
@@ -397,7 +959,17 @@ impl I2c {
     ///
     /// Returns how many bytes were written.
     pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
-        Ok(self.i2cdev.write(buffer)?)
+        let start = self.trace_start();
+        let result = self.retry(|| (&self.i2cdev).write(buffer));
+        self.trace(
+            start,
+            Direction::Write,
+            || buffer.to_vec(),
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
     }
 
     /// Sends the outgoing data contained in `write_buffer` to the slave device, and
@@ -416,15 +988,105 @@ impl I2c {
     /// [`write`]: #method.write
     /// [`read`]: #method.read
     pub fn write_read(&self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<()> {
-        ioctl::i2c_write_read(
-            self.i2cdev.as_raw_fd(),
-            self.address,
-            self.addr_10bit,
-            write_buffer,
-            read_buffer,
-        )?;
+        let start = self.trace_start();
+        let result = self.retry(|| {
+            ioctl::i2c_write_read(
+                self.i2cdev.as_raw_fd(),
+                self.address,
+                self.addr_10bit,
+                write_buffer,
+                read_buffer,
+            )
+        });
+        self.trace(
+            start,
+            Direction::Transfer,
+            || write_buffer.to_vec(),
+            &result,
+            |_| read_buffer.to_vec(),
+        );
+
+        result
+    }
 
-        Ok(())
+    /// Transfers multiple read and write messages in a single transaction.
+    ///
+    /// Each [`I2cMessage`] contains its own slave address and flags, and references
+    /// either a read buffer or a write buffer. The address set with
+    /// [`set_slave_address`] isn't used.
+    ///
+    /// Compared to calling [`write`] and [`read`] separately, `transaction` doesn't
+    /// issue a STOP condition in between messages. A repeated START is sent instead,
+    /// unless it's skipped with [`I2cMessage::set_nostart`]. A maximum of 42 messages
+    /// can be transferred in a single transaction.
+    ///
+    /// `transaction` returns `Err(`[`Error::FeatureNotSupported`]`)` if any of the
+    /// messages uses a flag that isn't supported by the underlying drivers, and
+    /// `Err(`[`Error::InvalidSlaveAddress`]`)` if any of the slave addresses are invalid.
+    /// If any of the messages references a buffer larger than 8192 bytes, an
+    /// `io::ErrorKind::InvalidInput` error is returned.
+    ///
+    /// Sequence: START → Address + R/W Bit → Incoming/Outgoing Bytes → Repeated START
+    /// → Address + R/W Bit → Incoming/Outgoing Bytes → ... → STOP
+    ///
+    /// [`I2cMessage`]: struct.I2cMessage.html
+    /// [`I2cMessage::set_nostart`]: struct.I2cMessage.html#method.set_nostart
+    /// [`set_slave_address`]: #method.set_slave_address
+    /// [`write`]: #method.write
+    /// [`read`]: #method.read
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    /// [`Error::InvalidSlaveAddress`]: enum.Error.html#variant.InvalidSlaveAddress
+    pub fn transaction(&self, messages: &mut [I2cMessage<'_>]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let capabilities = self.capabilities();
+        for message in messages.iter() {
+            if (message.addr_10bit() && !capabilities.addr_10bit())
+                || (message.nostart() && !capabilities.nostart())
+                || ((message.ignore_nak() || message.rev_dir_addr())
+                    && !capabilities.protocol_mangling())
+            {
+                return Err(Error::FeatureNotSupported);
+            }
+
+            if !is_valid_address(message.address(), message.addr_10bit()) {
+                return Err(Error::InvalidSlaveAddress(message.address()));
+            }
+        }
+
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::i2c_rdwr(self.i2cdev.as_raw_fd(), messages));
+
+        // Record each message separately, since they can have different addresses
+        if let (Some(tracer), Some(start)) = (&self.tracer, start) {
+            for message in messages.iter() {
+                let (direction, write_data, read_data) = if message.is_read() {
+                    let read_data = if result.is_ok() {
+                        message.data().to_vec()
+                    } else {
+                        Vec::new()
+                    };
+
+                    (Direction::Read, Vec::new(), read_data)
+                } else {
+                    (Direction::Write, message.data().to_vec(), Vec::new())
+                };
+
+                tracer.record(start.finish(
+                    Interface::I2c,
+                    Some(self.bus),
+                    Some(message.address()),
+                    direction,
+                    write_data,
+                    read_data,
+                    result.as_ref().err().map(|e| e.to_string()),
+                ));
+            }
+        }
+
+        result
     }
 
     /// Sends an 8-bit `command`, and then fills a multi-byte `buffer` with
@@ -442,9 +1104,17 @@ impl I2c {
     ///
     /// [`smbus_block_read`]: #method.smbus_block_read
     pub fn block_read(&self, command: u8, buffer: &mut [u8]) -> Result<()> {
-        ioctl::i2c_block_read(self.i2cdev.as_raw_fd(), command, buffer)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::i2c_block_read(self.i2cdev.as_raw_fd(), command, buffer));
+        self.trace(
+            start,
+            Direction::Transfer,
+            || vec![command],
+            &result,
+            |_| buffer.to_vec(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command` followed by a multi-byte `buffer`.
@@ -461,9 +1131,18 @@ impl I2c {
     ///
     /// [`smbus_block_write`]: #method.smbus_block_write
     pub fn block_write(&self, command: u8, buffer: &[u8]) -> Result<()> {
-        ioctl::i2c_block_write(self.i2cdev.as_raw_fd(), command, buffer)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::i2c_block_write(self.i2cdev.as_raw_fd(), command, buffer));
+        self.trace(
+            start,
+            Direction::Write,
+            || trace_data(command, &buffer[..buffer.len().min(32)]),
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
     }
 
     // Note: smbus_read/write_32/64 could theoretically be emulated using block_read/write
@@ -473,25 +1152,48 @@ impl I2c {
     ///
     /// Sequence: START → Address + Command Bit → STOP
     pub fn smbus_quick_command(&self, command: bool) -> Result<()> {
-        ioctl::smbus_quick_command(self.i2cdev.as_raw_fd(), command)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::smbus_quick_command(self.i2cdev.as_raw_fd(), command));
+
+        // The command bit takes the place of the R/W bit
+        let direction = if command {
+            Direction::Read
+        } else {
+            Direction::Write
+        };
+        self.trace(start, direction, Vec::new, &result, |_| Vec::new());
+
+        result
     }
 
     /// Receives an 8-bit value.
     ///
     /// Sequence: START → Address + Read Bit → Incoming Byte → STOP
     pub fn smbus_receive_byte(&self) -> Result<u8> {
-        Ok(ioctl::smbus_receive_byte(self.i2cdev.as_raw_fd())?)
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::smbus_receive_byte(self.i2cdev.as_raw_fd()));
+        self.trace(start, Direction::Read, Vec::new, &result, |&value| {
+            vec![value]
+        });
+
+        result
     }
 
     /// Sends an 8-bit `value`.
     ///
     /// Sequence: START → Address + Write Bit → Outgoing Byte → STOP
     pub fn smbus_send_byte(&self, value: u8) -> Result<()> {
-        ioctl::smbus_send_byte(self.i2cdev.as_raw_fd(), value)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::smbus_send_byte(self.i2cdev.as_raw_fd(), value));
+        self.trace(
+            start,
+            Direction::Write,
+            || vec![value],
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command`, and receives an 8-bit value.
@@ -499,16 +1201,35 @@ impl I2c {
     /// Sequence: START → Address + Write Bit → Command → Repeated START
     /// → Address + Read Bit → Incoming Byte → STOP
     pub fn smbus_read_byte(&self, command: u8) -> Result<u8> {
-        Ok(ioctl::smbus_read_byte(self.i2cdev.as_raw_fd(), command)?)
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::smbus_read_byte(self.i2cdev.as_raw_fd(), command));
+        self.trace(
+            start,
+            Direction::Transfer,
+            || vec![command],
+            &result,
+            |&value| vec![value],
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command` and an 8-bit `value`.
     ///
     /// Sequence: START → Address + Write Bit → Command → Outgoing Byte → STOP
     pub fn smbus_write_byte(&self, command: u8, value: u8) -> Result<()> {
-        ioctl::smbus_write_byte(self.i2cdev.as_raw_fd(), command, value)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::smbus_write_byte(self.i2cdev.as_raw_fd(), command, value));
+        self.trace(
+            start,
+            Direction::Write,
+            || vec![command, value],
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command`, and receives a 16-bit value.
@@ -523,7 +1244,17 @@ impl I2c {
     ///
     /// [`smbus_read_word_swapped`]: #method.smbus_read_word_swapped
     pub fn smbus_read_word(&self, command: u8) -> Result<u16> {
-        Ok(ioctl::smbus_read_word(self.i2cdev.as_raw_fd(), command)?)
+        let start = self.trace_start();
+        let result = self.retry(|| ioctl::smbus_read_word(self.i2cdev.as_raw_fd(), command));
+        self.trace(
+            start,
+            Direction::Transfer,
+            || vec![command],
+            &result,
+            |&value| value.to_le_bytes().to_vec(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command`, and receives a 16-bit `value` in a non-standard swapped byte order.
@@ -537,7 +1268,7 @@ impl I2c {
     ///
     /// [`smbus_read_word`]: #method.smbus_read_word
     pub fn smbus_read_word_swapped(&self, command: u8) -> Result<u16> {
-        let value = ioctl::smbus_read_word(self.i2cdev.as_raw_fd(), command)?;
+        let value = self.smbus_read_word(command)?;
 
         Ok(((value & 0xFF00) >> 8) | ((value & 0xFF) << 8))
     }
@@ -553,9 +1284,18 @@ impl I2c {
     ///
     /// [`smbus_write_word_swapped`]: #method.smbus_write_word_swapped
     pub fn smbus_write_word(&self, command: u8, value: u16) -> Result<()> {
-        ioctl::smbus_write_word(self.i2cdev.as_raw_fd(), command, value)?;
-
-        Ok(())
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::smbus_write_word(self.i2cdev.as_raw_fd(), command, value));
+        self.trace(
+            start,
+            Direction::Write,
+            || trace_data(command, &value.to_le_bytes()),
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command` and a 16-bit `value` in a non-standard swapped byte order.
@@ -567,13 +1307,7 @@ impl I2c {
     ///
     /// [`smbus_write_word`]: #method.smbus_write_word
     pub fn smbus_write_word_swapped(&self, command: u8, value: u16) -> Result<()> {
-        ioctl::smbus_write_word(
-            self.i2cdev.as_raw_fd(),
-            command,
-            ((value & 0xFF00) >> 8) | ((value & 0xFF) << 8),
-        )?;
-
-        Ok(())
+        self.smbus_write_word(command, ((value & 0xFF00) >> 8) | ((value & 0xFF) << 8))
     }
 
     /// Sends an 8-bit `command` and a 16-bit `value`, and then receives a 16-bit value in response.
@@ -589,11 +1323,18 @@ impl I2c {
     ///
     /// [`smbus_process_call_swapped`]: #method.smbus_process_call_swapped
     pub fn smbus_process_call(&self, command: u8, value: u16) -> Result<u16> {
-        Ok(ioctl::smbus_process_call(
-            self.i2cdev.as_raw_fd(),
-            command,
-            value,
-        )?)
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::smbus_process_call(self.i2cdev.as_raw_fd(), command, value));
+        self.trace(
+            start,
+            Direction::Transfer,
+            || trace_data(command, &value.to_le_bytes()),
+            &result,
+            |&response| response.to_le_bytes().to_vec(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command` and a 16-bit `value`, and then receives a 16-bit value in response, in
@@ -609,11 +1350,8 @@ impl I2c {
     ///
     /// [`smbus_process_call`]: #method.smbus_process_call
     pub fn smbus_process_call_swapped(&self, command: u8, value: u16) -> Result<u16> {
-        let response = ioctl::smbus_process_call(
-            self.i2cdev.as_raw_fd(),
-            command,
-            ((value & 0xFF00) >> 8) | ((value & 0xFF) << 8),
-        )?;
+        let response =
+            self.smbus_process_call(command, ((value & 0xFF00) >> 8) | ((value & 0xFF) << 8))?;
 
         Ok(((response & 0xFF00) >> 8) | ((response & 0xFF) << 8))
     }
@@ -644,11 +1382,18 @@ impl I2c {
             return Err(Error::FeatureNotSupported);
         }
 
-        Ok(ioctl::smbus_block_read(
-            self.i2cdev.as_raw_fd(),
-            command,
-            buffer,
-        )?)
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::smbus_block_read(self.i2cdev.as_raw_fd(), command, buffer));
+        self.trace(
+            start,
+            Direction::Transfer,
+            || vec![command],
+            &result,
+            |&len| buffer[..len.min(buffer.len())].to_vec(),
+        );
+
+        result
     }
 
     /// Sends an 8-bit `command` and an 8-bit byte count along with a multi-byte `buffer`.
@@ -659,9 +1404,111 @@ impl I2c {
     /// Sequence: START → Address + Write Bit → Command → Outgoing Byte Count
     /// → Outgoing Bytes → STOP
     pub fn smbus_block_write(&self, command: u8, buffer: &[u8]) -> Result<()> {
-        ioctl::smbus_block_write(self.i2cdev.as_raw_fd(), command, buffer)?;
+        let start = self.trace_start();
+        let result =
+            self.retry(|| ioctl::smbus_block_write(self.i2cdev.as_raw_fd(), command, buffer));
+        self.trace(
+            start,
+            Direction::Write,
+            || trace_data(command, &buffer[..buffer.len().min(32)]),
+            &result,
+            |_| Vec::new(),
+        );
+
+        result
+    }
 
-        Ok(())
+    /// Sends an 8-bit `command` and an 8-bit byte count along with a multi-byte
+    /// `write_buffer`, and then receives an 8-bit byte count along with a multi-byte
+    /// `read_buffer`.
+    ///
+    /// `smbus_block_process_call` currently isn't supported on the Raspberry Pi, and
+    /// returns `Err(`[`Error::FeatureNotSupported`]`)` unless underlying driver support
+    /// is detected.
+    ///
+    /// The combined length of the outgoing and incoming data can't exceed 32 bytes.
+    /// Any additional data contained in `write_buffer` is ignored.
+    ///
+    /// Sequence: START → Address + Write Bit → Command → Outgoing Byte Count →
+    /// Outgoing Bytes → Repeated START → Address + Read Bit → Incoming Byte Count →
+    /// Incoming Bytes → STOP
+    ///
+    /// Returns how many bytes were read.
+    ///
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn smbus_block_process_call(
+        &self,
+        command: u8,
+        write_buffer: &[u8],
+        read_buffer: &mut [u8],
+    ) -> Result<usize> {
+        if !self.capabilities().smbus_block_process_call() {
+            return Err(Error::FeatureNotSupported);
+        }
+
+        let start = self.trace_start();
+        let result = self.retry(|| {
+            ioctl::smbus_block_process_call(
+                self.i2cdev.as_raw_fd(),
+                command,
+                write_buffer,
+                read_buffer,
+            )
+        });
+        self.trace(
+            start,
+            Direction::Transfer,
+            || trace_data(command, &write_buffer[..write_buffer.len().min(32)]),
+            &result,
+            |&len| read_buffer[..len.min(read_buffer.len())].to_vec(),
+        );
+
+        result
+    }
+
+    /// Queries the SMBus Alert Response Address (ARA), and returns the address of
+    /// the slave device that's asserting the SMBALERT# signal.
+    ///
+    /// Slave devices that support SMBus alerts pull the shared SMBALERT# line low to
+    /// request attention from the master. `smbus_alert_response` sends a Receive Byte
+    /// to the ARA (0x0C). The alerting slave device with the lowest address responds
+    /// with its own address, and releases SMBALERT#. If multiple slave devices are
+    /// alerting, call `smbus_alert_response` repeatedly until it returns `None`.
+    ///
+    /// Returns `None` if no slave device responded. The current slave address is
+    /// restored afterwards.
+    ///
+    /// If the kernel's `smbus_alert` driver is bound to the bus, the ARA is
+    /// already in use, and `smbus_alert_response` returns an
+    /// `io::ErrorKind::ResourceBusy` error.
+    ///
+    /// Sequence: START → ARA + Read Bit → Incoming Address → STOP
+    pub fn smbus_alert_response(&mut self) -> Result<Option<u16>> {
+        let result = self.read_alert_response();
+
+        // Always try to restore the slave address, but report the original error if there was one
+        let restore = self.restore_slave_address();
+        let address = result?;
+        restore?;
+
+        Ok(address)
+    }
+
+    fn read_alert_response(&self) -> Result<Option<u16>> {
+        ioctl::set_slave_address(
+            self.i2cdev.as_raw_fd(),
+            c_ulong::from(ALERT_RESPONSE_ADDRESS),
+        )?;
+
+        match ioctl::smbus_receive_byte(self.i2cdev.as_raw_fd()) {
+            // The 7-bit address is followed by a (don't care) R/W bit
+            Ok(response) => Ok(Some(u16::from(response >> 1))),
+            // No slave devices are alerting
+            Err(ref e) if matches!(e.raw_os_error(), Some(libc::ENXIO) | Some(libc::EREMOTEIO)) => {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
     /// Enables or disables SMBus Packet Error Checking.
@@ -671,10 +1518,17 @@ impl I2c {
     ///
     /// The PEC is calculated on all message bytes except the START, STOP, ACK and NACK bits.
     ///
+    /// When a received PEC byte doesn't match, the SMBus methods return
+    /// `Err(`[`Error::InvalidPec`]`)`.
+    ///
     /// By default, `pec` is set to `false`.
+    ///
+    /// [`Error::InvalidPec`]: enum.Error.html#variant.InvalidPec
     pub fn set_smbus_pec(&self, pec: bool) -> Result<()> {
         ioctl::set_pec(self.i2cdev.as_raw_fd(), pec as c_ulong)?;
 
+        self.pec.set(pec);
+
         Ok(())
     }
 }
@@ -682,3 +1536,48 @@ impl I2c {
 // Send is safe for I2c, but we're marked !Send because of the dummy pointer that's
 // needed to force !Sync.
 unsafe impl Send for I2c {}
+
+// Returns the ID of the first I2C bus in /sys/bus/i2c/devices that matches predicate
+fn find_bus<F>(predicate: F) -> Result<u8>
+where
+    F: Fn(&Path) -> bool,
+{
+    for entry in std::fs::read_dir("/sys/bus/i2c/devices")? {
+        let entry = entry?;
+
+        // Skip slave devices (N-00XX), which are listed alongside the buses
+        let bus = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("i2c-"))
+            .and_then(|bus| bus.parse::<u8>().ok())
+        {
+            Some(bus) => bus,
+            None => continue,
+        };
+
+        if predicate(&entry.path()) {
+            return Ok(bus);
+        }
+    }
+
+    Err(Error::Io(io::Error::from(io::ErrorKind::NotFound)))
+}
+
+// Prepends an SMBus command byte to the outgoing data of a traced operation
+fn trace_data(command: u8, data: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(data.len() + 1);
+    buffer.push(command);
+    buffer.extend_from_slice(data);
+
+    buffer
+}
+
+// Filters out invalid and reserved addresses
+fn is_valid_address(address: u16, addr_10bit: bool) -> bool {
+    if addr_10bit {
+        address <= 0x03FF
+    } else {
+        (address >> 3) != 0b1111 && address <= 0x7F
+    }
+}
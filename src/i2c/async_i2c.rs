@@ -0,0 +1,106 @@
+use std::fmt;
+use std::io;
+
+use embedded_hal::i2c::Operation;
+
+use crate::worker::Worker;
+
+use super::{Error, I2c, Result};
+
+// Owned copy of an embedded-hal operation, which can be moved to the worker thread
+enum OwnedOperation {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+}
+
+/// Provides asynchronous access to an I2C bus.
+///
+/// `AsyncI2c` moves an [`I2c`] instance to a dedicated worker thread, where
+/// transactions are executed using blocking calls. Each transaction returns a
+/// future that resolves once the worker has completed it, which makes `AsyncI2c`
+/// usable with any async runtime.
+///
+/// All data is copied to and from the worker thread, so dropping a future before
+/// it resolves doesn't affect any borrowed buffers. The transaction itself is
+/// still completed in the background.
+///
+/// `AsyncI2c` implements the `embedded-hal-async` `I2c` trait, which can be
+/// enabled by specifying the optional `embedded-hal-async` feature in the dependency
+/// declaration for the `rppal` crate. Transactions follow the same rules as the
+/// `embedded-hal` `I2c` trait implementation for `I2c`.
+///
+/// [`I2c`]: struct.I2c.html
+pub struct AsyncI2c {
+    worker: Worker<I2c>,
+}
+
+impl AsyncI2c {
+    /// Constructs a new `AsyncI2c`, and starts its worker thread.
+    pub fn new(i2c: I2c) -> Result<AsyncI2c> {
+        Ok(AsyncI2c {
+            worker: Worker::new("rppal-i2c", i2c)?,
+        })
+    }
+
+    async fn transaction(&self, address: u8, operations: &mut [Operation<'_>]) -> Result<()> {
+        let mut owned: Vec<OwnedOperation> = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buffer) => OwnedOperation::Read(vec![0; buffer.len()]),
+                Operation::Write(buffer) => OwnedOperation::Write(buffer.to_vec()),
+            })
+            .collect();
+
+        let (result, owned) = self
+            .worker
+            .run(move |i2c| {
+                let mut operations: Vec<Operation<'_>> = owned
+                    .iter_mut()
+                    .map(|op| match op {
+                        OwnedOperation::Read(buffer) => Operation::Read(buffer),
+                        OwnedOperation::Write(buffer) => Operation::Write(buffer),
+                    })
+                    .collect();
+
+                let result = embedded_hal::i2c::I2c::transaction(i2c, address, &mut operations);
+                drop(operations);
+
+                (result, owned)
+            })
+            .await
+            .ok_or_else(worker_stopped)?;
+
+        result?;
+
+        for (op, owned) in operations.iter_mut().zip(owned) {
+            if let (Operation::Read(buffer), OwnedOperation::Read(data)) = (op, owned) {
+                buffer.copy_from_slice(&data);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AsyncI2c {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncI2c").finish_non_exhaustive()
+    }
+}
+
+impl embedded_hal::i2c::ErrorType for AsyncI2c {
+    type Error = Error;
+}
+
+impl embedded_hal_async::i2c::I2c for AsyncI2c {
+    async fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<()> {
+        AsyncI2c::transaction(self, address, operations).await
+    }
+}
+
+fn worker_stopped() -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "I2C worker thread stopped",
+    ))
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use super::{Error, I2c, Result};
+
+/// Provides shared access to an I2C bus.
+///
+/// `I2cBus` wraps an [`I2c`] instance, and hands out [`I2cDevice`] handles
+/// that can be used by different drivers, either on the same thread or on
+/// separate threads. Each access through an `I2cDevice` locks the bus for the
+/// duration of the operation.
+///
+/// Cloning an `I2cBus` creates a new reference to the same bus.
+///
+/// [`I2c`]: struct.I2c.html
+/// [`I2cDevice`]: struct.I2cDevice.html
+#[derive(Clone)]
+pub struct I2cBus {
+    i2c: Arc<Mutex<I2c>>,
+}
+
+impl I2cBus {
+    /// Constructs a new `I2cBus`.
+    ///
+    /// `i2c` can no longer be accessed directly. The slave address, 10-bit
+    /// addressing and Packet Error Checking settings are managed by each
+    /// [`I2cDevice`].
+    ///
+    /// [`I2cDevice`]: struct.I2cDevice.html
+    pub fn new(i2c: I2c) -> I2cBus {
+        I2cBus {
+            i2c: Arc::new(Mutex::new(i2c)),
+        }
+    }
+
+    /// Returns a new [`I2cDevice`] for the slave device at the specified `address`.
+    ///
+    /// The address is validated when the `I2cDevice` is first used.
+    ///
+    /// [`I2cDevice`]: struct.I2cDevice.html
+    pub fn device(&self, address: u16) -> I2cDevice {
+        I2cDevice {
+            bus: self.clone(),
+            address,
+            addr_10bit: false,
+            pec: false,
+        }
+    }
+
+    /// Locks the bus, and calls `f` with exclusive access to the underlying [`I2c`].
+    ///
+    /// Settings changed by `f` are overwritten by the next [`I2cDevice`] that
+    /// accesses the bus.
+    ///
+    /// [`I2c`]: struct.I2c.html
+    /// [`I2cDevice`]: struct.I2cDevice.html
+    pub fn access<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut I2c) -> Result<T>,
+    {
+        f(&mut self.i2c.lock().unwrap())
+    }
+}
+
+impl From<I2c> for I2cBus {
+    fn from(i2c: I2c) -> I2cBus {
+        I2cBus::new(i2c)
+    }
+}
+
+impl fmt::Debug for I2cBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("I2cBus").finish_non_exhaustive()
+    }
+}
+
+/// Provides access to a single slave device on a shared [`I2cBus`].
+///
+/// Each `I2cDevice` stores its own slave address, 10-bit addressing and Packet
+/// Error Checking settings. Before every access, the bus is locked, and any
+/// settings that differ from the bus's current configuration are applied. If
+/// the previous access was made by the same device, no additional ioctl calls
+/// are needed.
+///
+/// `I2cDevice` implements the `embedded-hal` `I2c` traits, which makes it
+/// possible for several drivers to share the same bus.
+///
+/// [`I2cBus`]: struct.I2cBus.html
+#[derive(Debug, Clone)]
+pub struct I2cDevice {
+    bus: I2cBus,
+    address: u16,
+    addr_10bit: bool,
+    pec: bool,
+}
+
+impl I2cDevice {
+    /// Returns the slave address.
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Returns `true` if 10-bit addressing is enabled for this device.
+    pub fn addr_10bit(&self) -> bool {
+        self.addr_10bit
+    }
+
+    /// Enables or disables 10-bit addressing for this device.
+    ///
+    /// Returns `Err(`[`Error::FeatureNotSupported`]`)` if 10-bit addresses
+    /// aren't supported by the underlying drivers.
+    ///
+    /// By default, `addr_10bit` is set to `false`.
+    ///
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn set_addr_10bit(&mut self, addr_10bit: bool) -> Result<()> {
+        if addr_10bit && !self.bus.access(|i2c| Ok(i2c.capabilities().addr_10bit()))? {
+            return Err(Error::FeatureNotSupported);
+        }
+
+        self.addr_10bit = addr_10bit;
+
+        Ok(())
+    }
+
+    /// Returns `true` if SMBus Packet Error Checking is enabled for this device.
+    pub fn smbus_pec(&self) -> bool {
+        self.pec
+    }
+
+    /// Enables or disables SMBus Packet Error Checking for this device.
+    ///
+    /// Returns `Err(`[`Error::FeatureNotSupported`]`)` if Packet Error
+    /// Checking isn't supported by the underlying drivers.
+    ///
+    /// By default, `pec` is set to `false`.
+    ///
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn set_smbus_pec(&mut self, pec: bool) -> Result<()> {
+        if pec && !self.bus.access(|i2c| Ok(i2c.capabilities().smbus_pec()))? {
+            return Err(Error::FeatureNotSupported);
+        }
+
+        self.pec = pec;
+
+        Ok(())
+    }
+
+    /// Locks the bus, selects this device, and calls `f` with exclusive access
+    /// to the underlying [`I2c`].
+    ///
+    /// `access` can be used for any of the I2C and SMBus methods that aren't
+    /// directly available on `I2cDevice`.
+    ///
+    /// [`I2c`]: struct.I2c.html
+    pub fn access<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut I2c) -> Result<T>,
+    {
+        self.bus.access(|i2c| {
+            self.select(i2c)?;
+            f(i2c)
+        })
+    }
+
+    /// Receives incoming data from the slave device and writes it to `buffer`.
+    ///
+    /// See [`I2c::read`] for more information.
+    ///
+    /// [`I2c::read`]: struct.I2c.html#method.read
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.access(|i2c| i2c.read(buffer))
+    }
+
+    /// Sends the outgoing data contained in `buffer` to the slave device.
+    ///
+    /// See [`I2c::write`] for more information.
+    ///
+    /// [`I2c::write`]: struct.I2c.html#method.write
+    pub fn write(&self, buffer: &[u8]) -> Result<usize> {
+        self.access(|i2c| i2c.write(buffer))
+    }
+
+    /// Sends the outgoing data contained in `write_buffer` to the slave device, and
+    /// then fills `read_buffer` with incoming data.
+    ///
+    /// See [`I2c::write_read`] for more information.
+    ///
+    /// [`I2c::write_read`]: struct.I2c.html#method.write_read
+    pub fn write_read(&self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<()> {
+        self.access(|i2c| i2c.write_read(write_buffer, read_buffer))
+    }
+
+    pub(crate) fn bus(&self) -> &I2cBus {
+        &self.bus
+    }
+
+    // Applies this device's settings, skipping any that are already active
+    fn select(&self, i2c: &mut I2c) -> Result<()> {
+        if i2c.addr_10bit != self.addr_10bit {
+            i2c.set_addr_10bit(self.addr_10bit)?;
+        }
+
+        if i2c.address != self.address {
+            i2c.set_slave_address(self.address)?;
+        }
+
+        if i2c.pec.get() != self.pec {
+            i2c.set_smbus_pec(self.pec)?;
+        }
+
+        Ok(())
+    }
+}
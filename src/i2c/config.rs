@@ -0,0 +1,96 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Transient errors that cause a failed transfer to be retried.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RetryOn {
+    /// Don't retry any failed transfers.
+    Never,
+    /// Retry transfers that weren't acknowledged by the slave device.
+    Nack,
+    /// Retry transfers that lost arbitration to another bus master.
+    ArbitrationLoss,
+    /// Retry transfers that weren't acknowledged, or that lost arbitration.
+    NackOrArbitrationLoss,
+}
+
+impl RetryOn {
+    // Checks whether err is one of the selected transient errors
+    pub(crate) fn matches(self, err: &io::Error) -> bool {
+        // Depending on the driver, a NACK is reported as ENXIO or EREMOTEIO, and
+        // arbitration loss as EAGAIN.
+        let nack = matches!(
+            err.raw_os_error(),
+            Some(libc::ENXIO) | Some(libc::EREMOTEIO)
+        );
+        let arbitration_loss = err.raw_os_error() == Some(libc::EAGAIN);
+
+        match self {
+            RetryOn::Never => false,
+            RetryOn::Nack => nack,
+            RetryOn::ArbitrationLoss => arbitration_loss,
+            RetryOn::NackOrArbitrationLoss => nack || arbitration_loss,
+        }
+    }
+}
+
+impl fmt::Display for RetryOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RetryOn::Never => write!(f, "Never"),
+            RetryOn::Nack => write!(f, "Nack"),
+            RetryOn::ArbitrationLoss => write!(f, "ArbitrationLoss"),
+            RetryOn::NackOrArbitrationLoss => write!(f, "NackOrArbitrationLoss"),
+        }
+    }
+}
+
+/// Configuration options applied when constructing an [`I2c`] instance.
+///
+/// Failed transfers are retried by RPPAL itself, rather than by the underlying
+/// drivers, which typically ignore the kernel's retry setting. Retries apply to
+/// all read, write, transaction and SMBus methods. Bus scanning, probing and
+/// Alert Response Address queries rely on NACKs to detect absent devices,
+/// and are never retried.
+///
+/// [`I2c`]: struct.I2c.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct I2cConfig {
+    /// Maximum duration of a transaction.
+    ///
+    /// `timeout` has a resolution of 10ms, and is rounded up to the nearest
+    /// multiple of 10ms. By default, `timeout` is set to 1 s,
+    /// which matches the kernel's default.
+    pub timeout: Duration,
+    /// Maximum number of times a failed transfer is retried.
+    ///
+    /// By default, `retries` is set to `0`.
+    pub retries: u32,
+    /// Transient errors that cause a failed transfer to be retried.
+    ///
+    /// By default, `retry_on` is set to `RetryOn::NackOrArbitrationLoss`.
+    pub retry_on: RetryOn,
+    /// Delay in between retries.
+    ///
+    /// By default, `backoff` is set to 1 ms.
+    pub backoff: Duration,
+}
+
+impl I2cConfig {
+    /// Constructs a new `I2cConfig` with the default settings.
+    pub fn new() -> I2cConfig {
+        I2cConfig::default()
+    }
+}
+
+impl Default for I2cConfig {
+    fn default() -> I2cConfig {
+        I2cConfig {
+            timeout: Duration::from_secs(1),
+            retries: 0,
+            retry_on: RetryOn::NackOrArbitrationLoss,
+            backoff: Duration::from_millis(1),
+        }
+    }
+}
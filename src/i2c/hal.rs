@@ -1,4 +1,4 @@
-use super::{Error, I2c};
+use super::{soft_i2c, Error, I2c, I2cDevice, I2cMessage, NoAcknowledgeSource, SoftI2c};
 
 #[cfg(feature = "embedded-hal-0")]
 impl embedded_hal_0::blocking::i2c::Write for I2c {
@@ -40,16 +40,18 @@ impl embedded_hal::i2c::ErrorType for I2c {
 #[cfg(feature = "embedded-hal")]
 impl embedded_hal::i2c::Error for Error {
     fn kind(&self) -> embedded_hal::i2c::ErrorKind {
-        if let Error::Io(e) = self {
-            use std::io::ErrorKind::*;
-
-            match e.kind() {
-                /* ResourceBusy | */ InvalidData => embedded_hal::i2c::ErrorKind::Bus,
-                WouldBlock => embedded_hal::i2c::ErrorKind::ArbitrationLoss,
-                _ => embedded_hal::i2c::ErrorKind::Other,
-            }
-        } else {
-            embedded_hal::i2c::ErrorKind::Other
+        use embedded_hal::i2c::{self, ErrorKind};
+
+        match self {
+            Error::NoAcknowledge(source) => ErrorKind::NoAcknowledge(match source {
+                NoAcknowledgeSource::Address => i2c::NoAcknowledgeSource::Address,
+                NoAcknowledgeSource::Data => i2c::NoAcknowledgeSource::Data,
+                NoAcknowledgeSource::Unknown => i2c::NoAcknowledgeSource::Unknown,
+            }),
+            Error::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            Error::BusError => ErrorKind::Bus,
+            Error::Io(e) if e.kind() == std::io::ErrorKind::InvalidData => ErrorKind::Bus,
+            _ => ErrorKind::Other,
         }
     }
 }
@@ -61,18 +63,198 @@ impl embedded_hal::i2c::I2c for I2c {
         address: u8,
         operations: &mut [embedded_hal::i2c::Operation],
     ) -> Result<(), Self::Error> {
-        self.set_slave_address(u16::from(address))?;
-        for op in operations {
-            match op {
-                embedded_hal::i2c::Operation::Read(buff) => {
-                    self.read(buff)?;
-                }
-                embedded_hal::i2c::Operation::Write(buff) => {
-                    self.write(buff)?;
-                }
-            }
+        i2c_transaction(self, u16::from(address), false, operations)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::Write for I2cDevice {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::write(self, address, bytes)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::Read for I2cDevice {
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::read(self, address, buffer)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::WriteRead for I2cDevice {
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::write_read(self, address, bytes, buffer)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::ErrorType for I2cDevice {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::I2c<embedded_hal::i2c::SevenBitAddress> for I2cDevice {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation],
+    ) -> Result<(), Self::Error> {
+        self.bus()
+            .access(|i2c| i2c_transaction(i2c, u16::from(address), false, operations))
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2cDevice {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation],
+    ) -> Result<(), Self::Error> {
+        self.bus()
+            .access(|i2c| i2c_transaction(i2c, address, true, operations))
+    }
+}
+
+// embedded-hal doesn't allow a repeated START in between adjacent operations of the
+// same type, so those are merged into a single message.
+#[cfg(feature = "embedded-hal")]
+fn i2c_transaction(
+    i2c: &I2c,
+    address: u16,
+    addr_10bit: bool,
+    operations: &mut [embedded_hal::i2c::Operation],
+) -> Result<(), Error> {
+    // Combined buffer for each group of adjacent operations, and the
+    // location of each operation's data within those buffers
+    let mut buffers: Vec<(bool, Vec<u8>)> = Vec::new();
+    let mut locations = Vec::with_capacity(operations.len());
+
+    for op in operations.iter() {
+        let read = matches!(op, embedded_hal::i2c::Operation::Read(_));
+        if !matches!(buffers.last(), Some((last_read, _)) if *last_read == read) {
+            buffers.push((read, Vec::new()));
+        }
+
+        let group = buffers.len() - 1;
+        let buffer = &mut buffers[group].1;
+        locations.push((group, buffer.len()));
+
+        match op {
+            embedded_hal::i2c::Operation::Read(buff) => buffer.resize(buffer.len() + buff.len(), 0),
+            embedded_hal::i2c::Operation::Write(buff) => buffer.extend_from_slice(buff),
         }
+    }
+
+    let mut messages: Vec<I2cMessage<'_>> = buffers
+        .iter_mut()
+        .map(|(read, buffer)| {
+            let mut message = if *read {
+                I2cMessage::with_read(address, buffer)
+            } else {
+                I2cMessage::with_write(address, buffer)
+            };
+            message.set_addr_10bit(addr_10bit);
+
+            message
+        })
+        .collect();
+
+    i2c.transaction(&mut messages)?;
+    drop(messages);
+
+    for (op, (group, offset)) in operations.iter_mut().zip(locations) {
+        if let embedded_hal::i2c::Operation::Read(buff) = op {
+            let len = buff.len();
+            buff.copy_from_slice(&buffers[group].1[offset..offset + len]);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::Write for SoftI2c {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::write(self, address, bytes)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::Read for SoftI2c {
+    type Error = Error;
 
-        Ok(())
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::read(self, address, buffer)
     }
 }
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::i2c::WriteRead for SoftI2c {
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::write_read(self, address, bytes, buffer)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::ErrorType for SoftI2c {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::I2c<embedded_hal::i2c::SevenBitAddress> for SoftI2c {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation],
+    ) -> Result<(), Self::Error> {
+        let mut operations = soft_i2c_operations(operations);
+        SoftI2c::transaction(self, u16::from(address), false, &mut operations)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for SoftI2c {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation],
+    ) -> Result<(), Self::Error> {
+        let mut operations = soft_i2c_operations(operations);
+        SoftI2c::transaction(self, address, true, &mut operations)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+fn soft_i2c_operations<'a>(
+    operations: &'a mut [embedded_hal::i2c::Operation<'_>],
+) -> Vec<soft_i2c::Operation<'a>> {
+    operations
+        .iter_mut()
+        .map(|op| match op {
+            embedded_hal::i2c::Operation::Read(buff) => soft_i2c::Operation::Read(buff),
+            embedded_hal::i2c::Operation::Write(buff) => soft_i2c::Operation::Write(buff),
+        })
+        .collect()
+}
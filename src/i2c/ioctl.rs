@@ -5,6 +5,8 @@ use std::result;
 
 use libc::{self, c_int, c_ulong, ioctl};
 
+use super::I2cMessage;
+
 #[cfg(target_env = "gnu")]
 type IoctlLong = libc::c_ulong;
 #[cfg(target_env = "musl")]
@@ -56,7 +58,8 @@ impl Capabilities {
         (self.funcs & FUNC_I2C) > 0
     }
 
-    pub(crate) fn slave(self) -> bool {
+    /// Indicates whether slave mode is supported.
+    pub fn slave(self) -> bool {
         (self.funcs & FUNC_SLAVE) > 0
     }
 
@@ -154,6 +157,7 @@ impl Capabilities {
 impl fmt::Debug for Capabilities {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Capabilities")
+            .field("slave", &self.slave())
             .field("addr_10bit", &self.addr_10bit())
             .field("i2c_block_read", &self.i2c_block_read())
             .field("i2c_block_write", &self.i2c_block_write())
@@ -186,12 +190,16 @@ const REQ_PEC: IoctlLong = 0x0708; // SMBus: Use Packet Error Checking
 const REQ_SMBUS: IoctlLong = 0x0720; // SMBus: Transfer data
 
 // NOTE: REQ_RETRIES - Supported in i2cdev, but not used in the underlying drivers
-// NOTE: REQ_RDWR - Only a single read operation is supported as the final message (see i2c-bcm2835.c)
+// NOTE: REQ_RDWR - Older kernels only support a single read operation as the final message (see i2c-bcm2835.c)
 
-const RDWR_FLAG_RD: u16 = 0x0001; // Read operation
-const RDWR_FLAG_TEN: u16 = 0x0010; // 10-bit slave address
+pub const RDWR_FLAG_RD: u16 = 0x0001; // Read operation
+pub const RDWR_FLAG_TEN: u16 = 0x0010; // 10-bit slave address
+pub const RDWR_FLAG_IGNORE_NAK: u16 = 0x1000; // Treat NACK as ACK (requires FUNC_PROTOCOL_MANGLING)
+pub const RDWR_FLAG_REV_DIR_ADDR: u16 = 0x2000; // Invert the R/W bit (requires FUNC_PROTOCOL_MANGLING)
+pub const RDWR_FLAG_NOSTART: u16 = 0x4000; // Skip the (repeated) START and address (requires FUNC_NOSTART)
 
 const RDWR_MSG_MAX: usize = 42; // Maximum messages per RDWR operation
+const RDWR_MSG_LEN_MAX: usize = 8192; // Maximum bytes per RDWR message
 const SMBUS_BLOCK_MAX: usize = 32; // Maximum bytes per block transfer
 
 // SMBus read or write request
@@ -210,6 +218,7 @@ enum SmbusSize {
     WordData = 3,
     ProcCall = 4,
     BlockData = 5,
+    BlockProcCall = 7,
     I2cBlockData = 8,
 }
 
@@ -439,6 +448,39 @@ pub fn smbus_block_write(fd: c_int, command: u8, value: &[u8]) -> Result<()> {
     )
 }
 
+pub fn smbus_block_process_call(
+    fd: c_int,
+    command: u8,
+    write_value: &[u8],
+    read_value: &mut [u8],
+) -> Result<usize> {
+    let mut buffer = SmbusBuffer::with_buffer(write_value);
+    smbus_request(
+        fd,
+        SmbusReadWrite::Write,
+        command,
+        SmbusSize::BlockProcCall,
+        Some(&mut buffer),
+    )?;
+
+    // Verify the length in case we're receiving corrupted data
+    let incoming_length = if buffer.data[0] as usize > SMBUS_BLOCK_MAX {
+        SMBUS_BLOCK_MAX
+    } else {
+        buffer.data[0] as usize
+    };
+
+    // Make sure the incoming data fits in the value buffer
+    let value_length = read_value.len();
+    if incoming_length > value_length {
+        read_value.copy_from_slice(&buffer.data[1..=value_length]);
+    } else {
+        read_value[..incoming_length].copy_from_slice(&buffer.data[1..=incoming_length]);
+    }
+
+    Ok(incoming_length)
+}
+
 pub fn i2c_block_read(fd: c_int, command: u8, value: &mut [u8]) -> Result<()> {
     let mut buffer = SmbusBuffer::new();
     buffer.data[0] = if value.len() > SMBUS_BLOCK_MAX {
@@ -471,30 +513,42 @@ pub fn i2c_block_write(fd: c_int, command: u8, value: &[u8]) -> Result<()> {
     )
 }
 
-// Specifies RDWR segment parameters
-#[repr(C)]
-#[derive(Debug, PartialEq, Copy, Clone)]
-struct RdwrSegment {
-    // Slave address
-    addr: u16,
-    // Segment flags
-    flags: u16,
-    // Buffer length
-    len: u16,
-    // Pointer to buffer
-    data: usize,
-}
-
 // Specifies RWDR request parameters
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone)]
-struct RdwrRequest {
-    // Pointer to an array of segments
-    segments: *mut [RdwrSegment],
-    // Number of segments
+struct RdwrRequest<'a> {
+    // Pointer to an array of messages
+    messages: *mut I2cMessage<'a>,
+    // Number of messages
     nmsgs: u32,
 }
 
+pub fn i2c_rdwr(fd: c_int, messages: &mut [I2cMessage<'_>]) -> Result<()> {
+    if messages.len() > RDWR_MSG_MAX {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+
+    // Oversized buffers are clamped to u16::MAX by I2cMessage, which still exceeds the limit
+    if messages
+        .iter()
+        .any(|message| message.len() > RDWR_MSG_LEN_MAX)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "I2C message exceeds 8192 bytes",
+        ));
+    }
+
+    let mut request = RdwrRequest {
+        messages: messages.as_mut_ptr(),
+        nmsgs: messages.len() as u32,
+    };
+
+    parse_retval!(unsafe { ioctl(fd, REQ_RDWR, &mut request) })?;
+
+    Ok(())
+}
+
 pub fn i2c_write_read(
     fd: c_int,
     address: u16,
@@ -507,33 +561,16 @@ pub fn i2c_write_read(
         return Ok(());
     }
 
-    let segment_write = RdwrSegment {
-        addr: address,
-        flags: if addr_10bit { RDWR_FLAG_TEN } else { 0 },
-        len: write_buffer.len() as u16,
-        data: write_buffer.as_ptr() as usize,
-    };
+    let mut messages = [
+        I2cMessage::with_write(address, write_buffer),
+        I2cMessage::with_read(address, read_buffer),
+    ];
 
-    let segment_read = RdwrSegment {
-        addr: address,
-        flags: if addr_10bit {
-            RDWR_FLAG_RD | RDWR_FLAG_TEN
-        } else {
-            RDWR_FLAG_RD
-        },
-        len: read_buffer.len() as u16,
-        data: read_buffer.as_mut_ptr() as usize,
-    };
-
-    let mut segments: [RdwrSegment; 2] = [segment_write, segment_read];
-    let mut request = RdwrRequest {
-        segments: &mut segments,
-        nmsgs: 2,
-    };
-
-    parse_retval!(unsafe { ioctl(fd, REQ_RDWR, &mut request) })?;
+    for message in &mut messages {
+        message.set_addr_10bit(addr_10bit);
+    }
 
-    Ok(())
+    i2c_rdwr(fd, &mut messages)
 }
 
 pub fn set_slave_address(fd: c_int, value: c_ulong) -> Result<()> {
@@ -554,17 +591,15 @@ pub fn set_pec(fd: c_int, value: c_ulong) -> Result<()> {
     Ok(())
 }
 
-pub fn set_timeout(fd: c_int, value: c_ulong) -> Result<()> {
-    // Timeout is specified in units of 10ms
-    let timeout: c_ulong = if value > 0 && value < 10 {
-        1
-    } else {
-        value / 10
-    };
+// Sets the timeout in milliseconds, and returns the timeout that was actually applied
+pub fn set_timeout(fd: c_int, value: u64) -> Result<u64> {
+    // Timeout is specified in units of 10ms. Round up, so short timeouts aren't
+    // disabled. Values that don't fit in an i32 return EINVAL.
+    let timeout = ((value / 10) + u64::from(value % 10 > 0)).min(i32::MAX as u64);
 
-    parse_retval!(unsafe { ioctl(fd, REQ_TIMEOUT, timeout) })?;
+    parse_retval!(unsafe { ioctl(fd, REQ_TIMEOUT, timeout as c_ulong) })?;
 
-    Ok(())
+    Ok(timeout * 10)
 }
 
 pub fn set_retries(fd: c_int, value: c_ulong) -> Result<()> {
@@ -0,0 +1,198 @@
+use std::fmt;
+use std::marker;
+
+use super::ioctl::{
+    RDWR_FLAG_IGNORE_NAK, RDWR_FLAG_NOSTART, RDWR_FLAG_RD, RDWR_FLAG_REV_DIR_ADDR, RDWR_FLAG_TEN,
+};
+
+/// Part of a multi-message transaction.
+///
+/// `I2cMessage`s are transferred using the [`I2c::transaction`] method.
+///
+/// Each `I2cMessage` contains its own slave address, and references either a
+/// read buffer or a write buffer. Construct a new `I2cMessage` for a read
+/// operation using [`with_read`], or for a write operation using [`with_write`].
+///
+/// Optional flags can be set that change how the message is transferred on
+/// the bus. Most flags depend on features that aren't supported by all
+/// underlying drivers.
+///
+/// [`I2c::transaction`]: struct.I2c.html#method.transaction
+/// [`with_read`]: #method.with_read
+/// [`with_write`]: #method.with_write
+#[derive(PartialEq, Eq, Copy, Clone)]
+#[repr(C)]
+pub struct I2cMessage<'a> {
+    // Slave address.
+    addr: u16,
+    // Message flags.
+    flags: u16,
+    // Number of bytes to transfer in this message.
+    len: u16,
+    // Pointer to the read or write buffer.
+    buf: usize,
+    // Zero-sized variable used to link this struct to the buffer lifetime.
+    buffer_lifetime: marker::PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> I2cMessage<'a> {
+    /// Constructs a new `I2cMessage`, and configures it for a read operation.
+    ///
+    /// Incoming data from the slave device at `address` is written to `buffer`.
+    /// The total number of bytes read depends on the length of `buffer`, which
+    /// can't exceed 8192 bytes. Transferring a message with a larger buffer returns
+    /// an `io::ErrorKind::InvalidInput` error.
+    pub fn with_read(address: u16, buffer: &'a mut [u8]) -> I2cMessage<'a> {
+        I2cMessage {
+            addr: address,
+            flags: RDWR_FLAG_RD,
+            // Oversized buffers are rejected before the message is transferred
+            len: buffer.len().min(u16::MAX as usize) as u16,
+            buf: buffer.as_mut_ptr() as usize,
+            buffer_lifetime: marker::PhantomData,
+        }
+    }
+
+    /// Constructs a new `I2cMessage`, and configures it for a write operation.
+    ///
+    /// Outgoing data from `buffer` is sent to the slave device at `address`.
+    /// The length of `buffer` can't exceed 8192 bytes. Transferring a message with
+    /// a larger buffer returns an `io::ErrorKind::InvalidInput` error.
+    pub fn with_write(address: u16, buffer: &'a [u8]) -> I2cMessage<'a> {
+        I2cMessage {
+            addr: address,
+            flags: 0,
+            // Oversized buffers are rejected before the message is transferred
+            len: buffer.len().min(u16::MAX as usize) as u16,
+            // The kernel only writes to the buffer for read operations
+            buf: buffer.as_ptr() as usize,
+            buffer_lifetime: marker::PhantomData,
+        }
+    }
+
+    /// Returns the slave address.
+    pub fn address(&self) -> u16 {
+        self.addr
+    }
+
+    // Returns the contents of the read or write buffer
+    pub(crate) fn data(&self) -> &[u8] {
+        // Safe because the message borrows the buffer for its entire lifetime
+        unsafe { std::slice::from_raw_parts(self.buf as *const u8, self.len as usize) }
+    }
+
+    /// Returns `true` if this message is configured for a read operation.
+    pub fn is_read(&self) -> bool {
+        (self.flags & RDWR_FLAG_RD) > 0
+    }
+
+    /// Returns the number of bytes that will be transferred.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if this message doesn't transfer any data.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the slave address is a 10-bit address.
+    pub fn addr_10bit(&self) -> bool {
+        (self.flags & RDWR_FLAG_TEN) > 0
+    }
+
+    /// Sets whether the slave address is a 10-bit address.
+    ///
+    /// 10-bit addresses require underlying driver support, which can be
+    /// checked with [`Capabilities::addr_10bit`].
+    ///
+    /// By default, `addr_10bit` is set to `false`.
+    ///
+    /// [`Capabilities::addr_10bit`]: struct.Capabilities.html#method.addr_10bit
+    pub fn set_addr_10bit(&mut self, addr_10bit: bool) {
+        self.set_flag(RDWR_FLAG_TEN, addr_10bit);
+    }
+
+    /// Returns `true` if the START condition and slave address are skipped.
+    pub fn nostart(&self) -> bool {
+        (self.flags & RDWR_FLAG_NOSTART) > 0
+    }
+
+    /// Skips the (repeated) START condition and slave address for this message.
+    ///
+    /// When `nostart` is enabled, the message's data is sent as a continuation of
+    /// the previous message, which is useful for devices that expect a single write
+    /// operation combined from multiple buffers. `nostart` is ignored for the first
+    /// message in a transaction.
+    ///
+    /// `nostart` requires underlying driver support. [`I2c::transaction`] returns
+    /// `Err(`[`Error::FeatureNotSupported`]`)` if the NOSTART flag isn't supported.
+    ///
+    /// By default, `nostart` is set to `false`.
+    ///
+    /// [`I2c::transaction`]: struct.I2c.html#method.transaction
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn set_nostart(&mut self, nostart: bool) {
+        self.set_flag(RDWR_FLAG_NOSTART, nostart);
+    }
+
+    /// Returns `true` if NACKs from the slave device are ignored.
+    pub fn ignore_nak(&self) -> bool {
+        (self.flags & RDWR_FLAG_IGNORE_NAK) > 0
+    }
+
+    /// Ignores any NACKs received from the slave device for this message,
+    /// and continues the transaction as if an ACK was received.
+    ///
+    /// `ignore_nak` requires protocol mangling support from the underlying
+    /// drivers. [`I2c::transaction`] returns `Err(`[`Error::FeatureNotSupported`]`)`
+    /// if protocol mangling isn't supported.
+    ///
+    /// By default, `ignore_nak` is set to `false`.
+    ///
+    /// [`I2c::transaction`]: struct.I2c.html#method.transaction
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn set_ignore_nak(&mut self, ignore_nak: bool) {
+        self.set_flag(RDWR_FLAG_IGNORE_NAK, ignore_nak);
+    }
+
+    /// Returns `true` if the R/W bit is inverted.
+    pub fn rev_dir_addr(&self) -> bool {
+        (self.flags & RDWR_FLAG_REV_DIR_ADDR) > 0
+    }
+
+    /// Inverts the R/W bit sent after the slave address for this message,
+    /// without changing the direction of the data transfer.
+    ///
+    /// `rev_dir_addr` is only needed for a small number of non-compliant slave
+    /// devices. It requires protocol mangling support from the underlying
+    /// drivers. [`I2c::transaction`] returns `Err(`[`Error::FeatureNotSupported`]`)`
+    /// if protocol mangling isn't supported.
+    ///
+    /// By default, `rev_dir_addr` is set to `false`.
+    ///
+    /// [`I2c::transaction`]: struct.I2c.html#method.transaction
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn set_rev_dir_addr(&mut self, rev_dir_addr: bool) {
+        self.set_flag(RDWR_FLAG_REV_DIR_ADDR, rev_dir_addr);
+    }
+
+    fn set_flag(&mut self, flag: u16, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+impl fmt::Debug for I2cMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("I2cMessage")
+            .field("addr", &self.addr)
+            .field("flags", &self.flags)
+            .field("len", &self.len)
+            .field("buf", &self.buf)
+            .finish()
+    }
+}
@@ -0,0 +1,531 @@
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::gpio::{Bias, IoPin, Level, Mode};
+
+use super::{is_valid_address, Error, NoAcknowledgeSource, Result};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// Default maximum duration a slave device is allowed to stretch the clock
+const CLOCK_STRETCH_TIMEOUT: Duration = Duration::from_millis(25);
+
+// Number of clock pulses used to release a slave device that's holding SDA low
+const RECOVERY_CLOCK_PULSES: u8 = 9;
+
+// Part of a transaction. Consecutive operations of the same type are
+// transferred without a repeated START in between.
+pub(crate) enum Operation<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// Provides a bit-banged I2C master on arbitrary GPIO pins.
+///
+/// `SoftI2c` emulates an I2C bus by switching the SDA and SCL pins between
+/// [`Mode::Input`] and [`Mode::Output`] to mimic open-drain outputs. A pin is
+/// released by changing it to an input, and pulled low by changing it to an
+/// output with its output state set to low. The bus still requires pull-up
+/// resistors. `SoftI2c` enables the built-in pull-up resistors on both pins,
+/// but these are usually too weak for anything but slow transfers over
+/// short distances.
+///
+/// Contrary to the BSC peripheral, `SoftI2c` fully supports clock stretching
+/// at any point during a transfer. It also supports 10-bit slave addresses,
+/// repeated START conditions between any number of read and write operations,
+/// and bus recovery through [`recover_bus`].
+///
+/// The clock speed is an upper limit. Due to function call overhead and
+/// scheduling/preemption, the actual clock frequency will be lower and
+/// may vary during a transfer.
+///
+/// Errors are reported using the same [`Error`] variants as [`I2c`]. A missing
+/// ACK returns [`Error::NoAcknowledge`] with the phase of the transfer that wasn't
+/// acknowledged, arbitration loss returns [`Error::ArbitrationLoss`], and a slave
+/// device that stretches the clock for longer than the timeout returns
+/// [`Error::Timeout`].
+///
+/// The `embedded-hal` trait implementations for `SoftI2c` can be enabled by specifying
+/// the optional `hal` feature in the dependency declaration for the `rppal` crate.
+///
+/// [`Error`]: enum.Error.html
+/// [`I2c`]: struct.I2c.html
+/// [`Error::NoAcknowledge`]: enum.Error.html#variant.NoAcknowledge
+/// [`Error::ArbitrationLoss`]: enum.Error.html#variant.ArbitrationLoss
+/// [`Error::Timeout`]: enum.Error.html#variant.Timeout
+/// [`Mode::Input`]: ../gpio/enum.Mode.html#variant.Input
+/// [`Mode::Output`]: ../gpio/enum.Mode.html#variant.Output
+/// [`recover_bus`]: #method.recover_bus
+pub struct SoftI2c {
+    sda: IoPin,
+    scl: IoPin,
+    clock_speed: u32,
+    half_period: Duration,
+    timeout: Duration,
+    addr_10bit: bool,
+    address: u16,
+}
+
+impl SoftI2c {
+    /// Constructs a new `SoftI2c`.
+    ///
+    /// `sda` and `scl` specify the pins used for the data and clock signals.
+    /// Both pins are released, and their built-in pull-up resistors are enabled.
+    ///
+    /// `clock_speed` defines the maximum clock frequency in hertz (Hz). Typical
+    /// values are 100 kHz (Standard-mode) and 400 kHz (Fast-mode).
+    pub fn new(mut sda: IoPin, mut scl: IoPin, clock_speed: u32) -> Result<SoftI2c> {
+        sda.set_bias(Bias::PullUp);
+        scl.set_bias(Bias::PullUp);
+
+        SoftI2c::with_io_pins(sda, scl, clock_speed)
+    }
+
+    // Constructs a new SoftI2c without changing the pull-up/pull-down resistors
+    pub(crate) fn with_io_pins(
+        mut sda: IoPin,
+        mut scl: IoPin,
+        clock_speed: u32,
+    ) -> Result<SoftI2c> {
+        for pin in [&mut sda, &mut scl] {
+            // Preset the output state, so switching the mode to output pulls the line low
+            pin.set_mode(Mode::Input);
+            pin.set_low();
+        }
+
+        let mut i2c = SoftI2c {
+            sda,
+            scl,
+            clock_speed: 0,
+            half_period: Duration::default(),
+            timeout: CLOCK_STRETCH_TIMEOUT,
+            addr_10bit: false,
+            address: 0,
+        };
+
+        i2c.set_clock_speed(clock_speed)?;
+
+        Ok(i2c)
+    }
+
+    /// Returns the maximum clock frequency in hertz (Hz).
+    pub fn clock_speed(&self) -> u32 {
+        self.clock_speed
+    }
+
+    /// Sets the maximum clock frequency in hertz (Hz).
+    ///
+    /// `clock_speed` must be greater than 0.
+    pub fn set_clock_speed(&mut self, clock_speed: u32) -> Result<()> {
+        if clock_speed == 0 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "clock speed must be greater than 0",
+            )));
+        }
+
+        self.clock_speed = clock_speed;
+        self.half_period = Duration::from_nanos(NANOS_PER_SEC / (u64::from(clock_speed) * 2));
+
+        Ok(())
+    }
+
+    /// Returns the maximum duration a slave device is allowed to stretch the clock.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets the maximum duration a slave device is allowed to stretch the clock.
+    ///
+    /// If SCL is held low for longer than `timeout`, the transaction is aborted
+    /// and returns `Err(`[`Error::Timeout`]`)`.
+    ///
+    /// By default, `timeout` is set to 25 ms.
+    ///
+    /// [`Error::Timeout`]: enum.Error.html#variant.Timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets a 7-bit or 10-bit slave address.
+    ///
+    /// `slave_address` refers to the slave device you're communicating with.
+    /// The specified address shouldn't include the R/W bit.
+    ///
+    /// By default, 10-bit addressing is disabled, which means
+    /// `set_slave_address` only accepts 7-bit addresses. 10-bit addressing
+    /// can be enabled with [`set_addr_10bit`].
+    ///
+    /// [`set_addr_10bit`]: #method.set_addr_10bit
+    pub fn set_slave_address(&mut self, slave_address: u16) -> Result<()> {
+        if !is_valid_address(slave_address, self.addr_10bit) {
+            return Err(Error::InvalidSlaveAddress(slave_address));
+        }
+
+        self.address = slave_address;
+
+        Ok(())
+    }
+
+    /// Enables or disables 10-bit addressing.
+    ///
+    /// By default, `addr_10bit` is set to `false`.
+    pub fn set_addr_10bit(&mut self, addr_10bit: bool) {
+        self.addr_10bit = addr_10bit;
+    }
+
+    /// Receives incoming data from the slave device and writes it to `buffer`.
+    ///
+    /// `read` reads as many bytes as can fit in `buffer`.
+    ///
+    /// Sequence: START → Address + Read Bit → Incoming Bytes → STOP
+    ///
+    /// Returns how many bytes were read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let len = buffer.len();
+        self.transaction(
+            self.address,
+            self.addr_10bit,
+            &mut [Operation::Read(buffer)],
+        )?;
+
+        Ok(len)
+    }
+
+    /// Sends the outgoing data contained in `buffer` to the slave device.
+    ///
+    /// Sequence: START → Address + Write Bit → Outgoing Bytes → STOP
+    ///
+    /// Returns how many bytes were written.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.transaction(
+            self.address,
+            self.addr_10bit,
+            &mut [Operation::Write(buffer)],
+        )?;
+
+        Ok(buffer.len())
+    }
+
+    /// Sends the outgoing data contained in `write_buffer` to the slave device, and
+    /// then fills `read_buffer` with incoming data.
+    ///
+    /// Compared to calling [`write`] and [`read`] separately, `write_read` doesn't
+    /// issue a STOP condition in between the write and read operation. A repeated
+    /// START is sent instead.
+    ///
+    /// Sequence: START → Address + Write Bit → Outgoing Bytes → Repeated START →
+    /// Address + Read Bit → Incoming Bytes → STOP
+    ///
+    /// [`write`]: #method.write
+    /// [`read`]: #method.read
+    pub fn write_read(&mut self, write_buffer: &[u8], read_buffer: &mut [u8]) -> Result<()> {
+        self.transaction(
+            self.address,
+            self.addr_10bit,
+            &mut [Operation::Write(write_buffer), Operation::Read(read_buffer)],
+        )
+    }
+
+    /// Attempts to release a slave device that's holding SDA low.
+    ///
+    /// A slave device can get stuck in the middle of a transfer when the master
+    /// is reset, and will keep SDA low while it's waiting for more clock pulses.
+    /// `recover_bus` sends up to 9 clock pulses until SDA is released, followed
+    /// by a STOP condition.
+    ///
+    /// Returns `Err(`[`Error::Timeout`]`)` if a slave device is holding SCL low,
+    /// or `Err(`[`Error::BusError`]`)` if SDA is still held low afterwards.
+    ///
+    /// [`Error::Timeout`]: enum.Error.html#variant.Timeout
+    /// [`Error::BusError`]: enum.Error.html#variant.BusError
+    pub fn recover_bus(&mut self) -> Result<()> {
+        self.release_sda();
+        self.release_scl()?;
+        self.wait();
+
+        for _ in 0..RECOVERY_CLOCK_PULSES {
+            if self.sda.read() == Level::High {
+                break;
+            }
+
+            self.pull_scl();
+            self.wait();
+            self.release_scl()?;
+            self.wait();
+        }
+
+        // Finish with a STOP condition to reset the slave device's state machine
+        self.pull_scl();
+        self.pull_sda();
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        self.release_sda();
+        self.wait();
+
+        if self.sda.read() == Level::Low {
+            return Err(Error::BusError);
+        }
+
+        Ok(())
+    }
+
+    // Transfers all operations in a single transaction, with a repeated START
+    // whenever the transfer direction changes.
+    pub(crate) fn transaction(
+        &mut self,
+        address: u16,
+        addr_10bit: bool,
+        operations: &mut [Operation<'_>],
+    ) -> Result<()> {
+        if !is_valid_address(address, addr_10bit) {
+            return Err(Error::InvalidSlaveAddress(address));
+        }
+
+        let result = self.transfer(address, addr_10bit, operations);
+
+        // Always try to release the bus, but report the original error if there was one
+        let stop = self.stop();
+
+        result.and(stop)
+    }
+
+    fn transfer(
+        &mut self,
+        address: u16,
+        addr_10bit: bool,
+        operations: &mut [Operation<'_>],
+    ) -> Result<()> {
+        // Direction of the previous operation. None before the first START.
+        let mut prev_read: Option<bool> = None;
+        // Tracks whether the 10-bit address has been sent in full after the last START
+        let mut addressed_10bit = false;
+
+        let len = operations.len();
+        for index in 0..len {
+            let next_read = match operations.get(index + 1) {
+                Some(Operation::Read(_)) => Some(true),
+                Some(Operation::Write(_)) => Some(false),
+                None => None,
+            };
+
+            match operations[index] {
+                Operation::Write(buffer) => {
+                    if prev_read != Some(false) {
+                        self.start()?;
+                        self.send_address(address, addr_10bit, false, &mut addressed_10bit)?;
+                    }
+
+                    for byte in buffer {
+                        if !self.write_byte(*byte)? {
+                            return Err(Error::NoAcknowledge(NoAcknowledgeSource::Data));
+                        }
+                    }
+
+                    prev_read = Some(false);
+                }
+                Operation::Read(ref mut buffer) => {
+                    if prev_read != Some(true) {
+                        self.start()?;
+                        self.send_address(address, addr_10bit, true, &mut addressed_10bit)?;
+                    }
+
+                    // NACK the final byte before a STOP or a repeated START
+                    let last = buffer.len().wrapping_sub(1);
+                    for (i, byte) in buffer.iter_mut().enumerate() {
+                        *byte = self.read_byte(i != last || next_read == Some(true))?;
+                    }
+
+                    prev_read = Some(true);
+                }
+            }
+        }
+
+        // A transaction without any operations only sends the address
+        if prev_read.is_none() {
+            self.start()?;
+            self.send_address(address, addr_10bit, false, &mut addressed_10bit)?;
+        }
+
+        Ok(())
+    }
+
+    fn send_address(
+        &mut self,
+        address: u16,
+        addr_10bit: bool,
+        read: bool,
+        addressed_10bit: &mut bool,
+    ) -> Result<()> {
+        let acked = if addr_10bit {
+            // 11110 + A9 + A8
+            let header = 0xF0 | ((address >> 7) as u8 & 0x06);
+
+            if read && *addressed_10bit {
+                // After a repeated START, a read only requires the first address byte
+                self.write_byte(header | 0x01)?
+            } else if read {
+                // Select the slave device with a write, then switch to read after a repeated START
+                let acked = self.write_byte(header)? && self.write_byte(address as u8)?;
+
+                if acked {
+                    self.start()?;
+                    self.write_byte(header | 0x01)?
+                } else {
+                    false
+                }
+            } else {
+                self.write_byte(header)? && self.write_byte(address as u8)?
+            }
+        } else {
+            self.write_byte(((address as u8) << 1) | read as u8)?
+        };
+
+        if !acked {
+            return Err(Error::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+
+        *addressed_10bit = addr_10bit;
+
+        Ok(())
+    }
+
+    // Sends a START or repeated START condition
+    fn start(&mut self) -> Result<()> {
+        self.release_sda();
+        self.wait();
+        self.release_scl()?;
+
+        // Another master or a stuck slave device is holding SDA low
+        if self.sda.read() == Level::Low {
+            return Err(Error::ArbitrationLoss);
+        }
+
+        self.wait();
+        self.pull_sda();
+        self.wait();
+        self.pull_scl();
+
+        Ok(())
+    }
+
+    // Sends a STOP condition
+    fn stop(&mut self) -> Result<()> {
+        self.pull_sda();
+        self.wait();
+        self.release_scl()?;
+        self.wait();
+        self.release_sda();
+        self.wait();
+
+        if self.sda.read() == Level::Low {
+            return Err(Error::ArbitrationLoss);
+        }
+
+        Ok(())
+    }
+
+    // Sends a single byte, and returns true if it was acknowledged
+    fn write_byte(&mut self, byte: u8) -> Result<bool> {
+        for bit in (0..8).rev() {
+            self.write_bit((byte >> bit) & 0x01 != 0)?;
+        }
+
+        Ok(!self.read_bit()?)
+    }
+
+    // Receives a single byte, followed by an ACK or NACK
+    fn read_byte(&mut self, ack: bool) -> Result<u8> {
+        let mut byte: u8 = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+
+        self.write_bit(!ack)?;
+
+        Ok(byte)
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<()> {
+        if bit {
+            self.release_sda();
+        } else {
+            self.pull_sda();
+        }
+
+        self.wait();
+        self.release_scl()?;
+
+        // Someone else is pulling SDA low while we're releasing it
+        if bit && self.sda.read() == Level::Low {
+            return Err(Error::ArbitrationLoss);
+        }
+
+        self.wait();
+        self.pull_scl();
+
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool> {
+        self.release_sda();
+        self.wait();
+        self.release_scl()?;
+        let bit = self.sda.read() == Level::High;
+        self.wait();
+        self.pull_scl();
+
+        Ok(bit)
+    }
+
+    #[inline(always)]
+    fn pull_sda(&mut self) {
+        self.sda.set_mode(Mode::Output);
+    }
+
+    #[inline(always)]
+    fn release_sda(&mut self) {
+        self.sda.set_mode(Mode::Input);
+    }
+
+    #[inline(always)]
+    fn pull_scl(&mut self) {
+        self.scl.set_mode(Mode::Output);
+    }
+
+    // Releases SCL, and waits for any slave devices that are stretching the clock
+    fn release_scl(&mut self) -> Result<()> {
+        self.scl.set_mode(Mode::Input);
+
+        let start = Instant::now();
+        while self.scl.read() == Level::Low {
+            if start.elapsed() > self.timeout {
+                return Err(Error::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Busy-waits for half a clock period
+    #[inline(always)]
+    fn wait(&self) {
+        let start = Instant::now();
+        while start.elapsed() < self.half_period {}
+    }
+}
+
+impl fmt::Debug for SoftI2c {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftI2c")
+            .field("sda", &self.sda.pin())
+            .field("scl", &self.scl.pin())
+            .field("clock_speed", &self.clock_speed)
+            .field("timeout", &self.timeout)
+            .field("addr_10bit", &self.addr_10bit)
+            .field("address", &self.address)
+            .finish()
+    }
+}
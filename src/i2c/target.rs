@@ -0,0 +1,237 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use super::{is_valid_address, Error, I2c, Result};
+
+// Added to the slave address to indicate the backend should be instantiated as a local slave
+const OWN_SLAVE_ADDRESS: u16 = 0x1000;
+
+// Maximum time to wait for the backend driver to be bound to the new device
+const BIND_TIMEOUT: Duration = Duration::from_secs(1);
+const BIND_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Memory layouts emulated by the `i2c-slave-eeprom` backend.
+///
+/// The memory layout determines the size of the memory window, and how many
+/// address bytes the bus master sends before reading or writing data.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TargetMemory {
+    /// 24C02 EEPROM. 256 bytes, 1 address byte.
+    Eeprom24c02,
+    /// 24C32 EEPROM. 4 KiB, 2 address bytes.
+    Eeprom24c32,
+    /// 24C64 EEPROM. 8 KiB, 2 address bytes.
+    Eeprom24c64,
+    /// 24C512 EEPROM. 64 KiB, 2 address bytes.
+    Eeprom24c512,
+}
+
+impl TargetMemory {
+    /// Returns the size of the memory window in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            TargetMemory::Eeprom24c02 => 256,
+            TargetMemory::Eeprom24c32 => 4096,
+            TargetMemory::Eeprom24c64 => 8192,
+            TargetMemory::Eeprom24c512 => 65536,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TargetMemory::Eeprom24c02 => "slave-24c02",
+            TargetMemory::Eeprom24c32 => "slave-24c32",
+            TargetMemory::Eeprom24c64 => "slave-24c64",
+            TargetMemory::Eeprom24c512 => "slave-24c512",
+        }
+    }
+}
+
+impl fmt::Display for TargetMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TargetMemory::Eeprom24c02 => write!(f, "Eeprom24c02"),
+            TargetMemory::Eeprom24c32 => write!(f, "Eeprom24c32"),
+            TargetMemory::Eeprom24c64 => write!(f, "Eeprom24c64"),
+            TargetMemory::Eeprom24c512 => write!(f, "Eeprom24c512"),
+        }
+    }
+}
+
+/// Provides access to a local I2C slave (target) device.
+///
+/// `Target` instantiates the kernel's `i2c-slave-eeprom` backend on the
+/// selected bus, which responds to an external bus master at the specified
+/// slave address as if it were an EEPROM. Data written by the bus master can be
+/// retrieved with [`read`], and data served to the bus master can be set with
+/// [`write`]. Both access the same memory window.
+///
+/// The backend is removed when `Target` goes out of scope.
+///
+/// Slave mode requires support from the I2C bus driver, and root privileges
+/// to instantiate the backend. More information can be found [here].
+///
+/// [`read`]: #method.read
+/// [`write`]: #method.write
+/// [here]: index.html#slave-mode
+pub struct Target {
+    bus: u8,
+    address: u16,
+    memory: TargetMemory,
+    read_only: bool,
+    eeprom: File,
+}
+
+impl Target {
+    /// Constructs a new `Target`.
+    ///
+    /// `bus` selects the I2C bus, `address` sets the 7-bit slave address the
+    /// backend responds to, and `memory` selects the emulated memory layout.
+    ///
+    /// Returns `Err(`[`Error::FeatureNotSupported`]`)` if the bus driver doesn't
+    /// support slave mode, or the `i2c-slave-eeprom` backend isn't available.
+    ///
+    /// [`Error::FeatureNotSupported`]: enum.Error.html#variant.FeatureNotSupported
+    pub fn new(bus: u8, address: u16, memory: TargetMemory) -> Result<Target> {
+        Target::with_settings(bus, address, memory, false)
+    }
+
+    /// Constructs a new `Target` that ignores any writes from the bus master.
+    ///
+    /// The memory window can still be changed locally with [`write`].
+    ///
+    /// [`write`]: #method.write
+    pub fn with_read_only(bus: u8, address: u16, memory: TargetMemory) -> Result<Target> {
+        Target::with_settings(bus, address, memory, true)
+    }
+
+    fn with_settings(
+        bus: u8,
+        address: u16,
+        memory: TargetMemory,
+        read_only: bool,
+    ) -> Result<Target> {
+        if !is_valid_address(address, false) {
+            return Err(Error::InvalidSlaveAddress(address));
+        }
+
+        if !I2c::with_bus(bus)?.capabilities().slave() {
+            return Err(Error::FeatureNotSupported);
+        }
+
+        let name = if read_only {
+            format!("{}ro", memory.name())
+        } else {
+            memory.name().to_owned()
+        };
+
+        // The device description needs to be written in a single operation
+        File::create(format!("/sys/bus/i2c/devices/i2c-{}/new_device", bus))?
+            .write_all(format!("{} 0x{:04x}", name, address | OWN_SLAVE_ADDRESS).as_bytes())?;
+
+        // Wait for the backend driver to be loaded and bound to the new device
+        let path = format!(
+            "/sys/bus/i2c/devices/{}-{:04x}/slave-eeprom",
+            bus,
+            address | OWN_SLAVE_ADDRESS
+        );
+
+        let mut elapsed = Duration::default();
+        while !Path::new(&path).exists() {
+            if elapsed >= BIND_TIMEOUT {
+                let _ = delete_device(bus, address);
+                return Err(Error::FeatureNotSupported);
+            }
+
+            thread::sleep(BIND_POLL_INTERVAL);
+            elapsed += BIND_POLL_INTERVAL;
+        }
+
+        let eeprom = match OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(eeprom) => eeprom,
+            Err(e) => {
+                let _ = delete_device(bus, address);
+                return Err(Error::Io(e));
+            }
+        };
+
+        Ok(Target {
+            bus,
+            address,
+            memory,
+            read_only,
+            eeprom,
+        })
+    }
+
+    /// Returns the I2C bus ID.
+    pub fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// Returns the slave address.
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Returns the emulated memory layout.
+    pub fn memory(&self) -> TargetMemory {
+        self.memory
+    }
+
+    /// Returns `true` if writes from the bus master are ignored.
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Reads the contents of the memory window, starting at `offset`, and writes
+    /// it to `buffer`.
+    ///
+    /// `read` returns the most recent data written by the bus master, or by [`write`].
+    ///
+    /// Returns how many bytes were read.
+    ///
+    /// [`write`]: #method.write
+    pub fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<usize> {
+        Ok(self.eeprom.read_at(buffer, offset as u64)?)
+    }
+
+    /// Writes the contents of `buffer` to the memory window, starting at `offset`.
+    ///
+    /// The data is served to the bus master on subsequent read operations.
+    ///
+    /// Returns how many bytes were written.
+    pub fn write(&self, offset: usize, buffer: &[u8]) -> Result<usize> {
+        Ok(self.eeprom.write_at(buffer, offset as u64)?)
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        let _ = delete_device(self.bus, self.address);
+    }
+}
+
+impl fmt::Debug for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Target")
+            .field("bus", &self.bus)
+            .field("address", &self.address)
+            .field("memory", &self.memory)
+            .field("read_only", &self.read_only)
+            .field("eeprom", &self.eeprom)
+            .finish()
+    }
+}
+
+fn delete_device(bus: u8, address: u16) -> Result<()> {
+    File::create(format!("/sys/bus/i2c/devices/i2c-{}/delete_device", bus))?
+        .write_all(format!("0x{:04x}", address | OWN_SLAVE_ADDRESS).as_bytes())?;
+
+    Ok(())
+}
@@ -20,6 +20,7 @@
 #[macro_use]
 mod macros;
 
+pub mod dht;
 pub mod gpio;
 #[cfg(any(
     feature = "embedded-hal-0",
@@ -28,7 +29,13 @@ pub mod gpio;
 ))]
 pub mod hal;
 pub mod i2c;
+pub mod onewire;
+pub mod pmbus;
 pub mod pwm;
+pub mod register;
 pub mod spi;
 pub mod system;
+pub mod trace;
 pub mod uart;
+#[cfg(feature = "embedded-hal-async")]
+mod worker;
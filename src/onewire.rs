@@ -0,0 +1,455 @@
+//! Interface for 1-Wire devices.
+//!
+//! RPPAL implements a bit-banged 1-Wire master on any available GPIO pin,
+//! without relying on the kernel's `w1-gpio` driver. The pin is used as an
+//! emulated open-drain output by switching it between [`Mode::Input`] (released)
+//! and [`Mode::Output`] (pulled low).
+//!
+//! A 1-Wire bus requires a pull-up resistor on the data line. The built-in
+//! pull-up resistor is enabled automatically, but it's too weak for reliable
+//! communication. Add an external 4.7 kΩ pull-up resistor between the data line
+//! and 3.3 V. Parasite-powered devices aren't supported.
+//!
+//! ## Timing
+//!
+//! 1-Wire uses time slots of 60 to 480 µs, and relies on pulses as short as
+//! 6 µs. [`OneWire`] uses busy-waiting to meet those timing requirements,
+//! but a time slot can still get corrupted when the thread is preempted.
+//! All ROM codes and scratchpads are verified with a CRC-8 checksum, and
+//! [`Error::InvalidCrc`] is returned when the data doesn't match. Retrying
+//! the operation is usually sufficient. Running your application with a
+//! real-time scheduling policy reduces the number of corrupted transfers.
+//!
+//! ## DS18B20
+//!
+//! The [`Ds18b20`] helper supports temperature conversions for the DS18B20,
+//! DS18S20, DS1822 and DS1825 temperature sensors.
+//!
+//! [`Mode::Input`]: ../gpio/enum.Mode.html#variant.Input
+//! [`Mode::Output`]: ../gpio/enum.Mode.html#variant.Output
+//! [`OneWire`]: struct.OneWire.html
+//! [`Error::InvalidCrc`]: enum.Error.html#variant.InvalidCrc
+//! [`Ds18b20`]: struct.Ds18b20.html
+
+use std::error;
+use std::fmt;
+use std::result;
+use std::time::{Duration, Instant};
+
+use crate::gpio::{Bias, IoPin, Level, Mode};
+
+mod ds18b20;
+
+pub use self::ds18b20::{Ds18b20, Resolution};
+
+// ROM commands
+const CMD_SEARCH_ROM: u8 = 0xF0;
+const CMD_READ_ROM: u8 = 0x33;
+const CMD_MATCH_ROM: u8 = 0x55;
+const CMD_SKIP_ROM: u8 = 0xCC;
+const CMD_ALARM_SEARCH: u8 = 0xEC;
+
+// Standard speed timing in microseconds (µs), based on Maxim Integrated application note 126
+const RESET_LOW: u64 = 480;
+const RESET_PRESENCE: u64 = 70;
+const RESET_RECOVERY: u64 = 410;
+const WRITE_1_LOW: u64 = 6;
+const WRITE_1_RECOVERY: u64 = 64;
+const WRITE_0_LOW: u64 = 60;
+const WRITE_0_RECOVERY: u64 = 10;
+const READ_LOW: u64 = 6;
+const READ_SAMPLE: u64 = 9;
+const READ_RECOVERY: u64 = 55;
+
+/// Errors that can occur when communicating with 1-Wire devices.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Error {
+    /// No presence pulse.
+    ///
+    /// None of the devices on the bus responded to a reset pulse. Make sure
+    /// the devices are powered, and an external pull-up resistor is connected.
+    NoPresence,
+    /// Bus is held low.
+    ///
+    /// The data line is still low after it was released, which usually indicates
+    /// a short circuit, or a missing pull-up resistor.
+    BusShorted,
+    /// CRC mismatch.
+    ///
+    /// The CRC-8 checksum of the received data doesn't match. The data was
+    /// most likely corrupted during the transfer.
+    InvalidCrc,
+    /// Unsupported device family.
+    ///
+    /// The family code of the ROM code isn't supported by the selected device helper.
+    UnsupportedFamily(u8),
+    /// Timed out.
+    ///
+    /// The device didn't complete the requested operation in time.
+    TimedOut,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::NoPresence => write!(f, "No presence pulse detected"),
+            Error::BusShorted => write!(f, "Bus is held low"),
+            Error::InvalidCrc => write!(f, "CRC mismatch"),
+            Error::UnsupportedFamily(family) => {
+                write!(f, "Unsupported device family: 0x{:02x}", family)
+            }
+            Error::TimedOut => write!(f, "Timed out"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Result type returned from methods that can have `onewire::Error`s.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Calculates the Dallas/Maxim CRC-8 checksum of `data`.
+///
+/// Calculating the CRC-8 of data that includes its own (valid) checksum
+/// as the final byte results in 0.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+
+    for byte in data {
+        let mut byte = *byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 0x01;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+
+    crc
+}
+
+/// 64-bit 1-Wire ROM code.
+///
+/// Every 1-Wire device has a unique ROM code, consisting of an 8-bit family
+/// code, a 48-bit serial number and an 8-bit CRC.
+///
+/// `Rom` is displayed in the same format the kernel's `w1` subsystem uses,
+/// for instance `28-0316a2795dff`.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Rom([u8; 8]);
+
+impl Rom {
+    /// Constructs a new `Rom` from its 8 bytes, in the order they're transferred
+    /// on the bus (family code first, CRC last).
+    ///
+    /// Returns `Err(`[`Error::InvalidCrc`]`)` if the CRC doesn't match.
+    ///
+    /// [`Error::InvalidCrc`]: enum.Error.html#variant.InvalidCrc
+    pub fn new(bytes: [u8; 8]) -> Result<Rom> {
+        if crc8(&bytes) != 0 {
+            return Err(Error::InvalidCrc);
+        }
+
+        Ok(Rom(bytes))
+    }
+
+    /// Returns the 8-bit family code.
+    pub fn family(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns the 48-bit serial number.
+    pub fn serial(&self) -> u64 {
+        self.0[1..7]
+            .iter()
+            .rev()
+            .fold(0u64, |serial, byte| (serial << 8) | u64::from(*byte))
+    }
+
+    /// Returns the 8-bit CRC.
+    pub fn crc(&self) -> u8 {
+        self.0[7]
+    }
+
+    /// Returns the ROM code's 8 bytes, in the order they're transferred on the bus.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.0
+    }
+}
+
+impl fmt::Display for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}-{:012x}", self.family(), self.serial())
+    }
+}
+
+/// Provides access to devices on a bit-banged 1-Wire bus.
+///
+/// More information on the required hardware and timing limitations can
+/// be found [here].
+///
+/// [here]: index.html
+#[derive(Debug)]
+pub struct OneWire {
+    pin: IoPin,
+}
+
+impl OneWire {
+    /// Constructs a new `OneWire`.
+    ///
+    /// `pin` specifies the data line. The pin's built-in pull-up resistor is
+    /// enabled, and the pin is released.
+    pub fn new(mut pin: IoPin) -> OneWire {
+        // Preset the output state, so switching the mode to output pulls the line low
+        pin.set_mode(Mode::Input);
+        pin.set_bias(Bias::PullUp);
+        pin.set_low();
+
+        OneWire { pin }
+    }
+
+    /// Sends a reset pulse, and returns `true` if any devices responded with
+    /// a presence pulse.
+    ///
+    /// Returns `Err(`[`Error::BusShorted`]`)` if the data line is low before
+    /// the reset pulse, or stays low afterwards.
+    ///
+    /// [`Error::BusShorted`]: enum.Error.html#variant.BusShorted
+    pub fn reset(&mut self) -> Result<bool> {
+        self.release();
+        if self.pin.read() == Level::Low {
+            return Err(Error::BusShorted);
+        }
+
+        self.pull_low();
+        delay_us(RESET_LOW);
+
+        let start = Instant::now();
+        self.release();
+        wait_until(start, RESET_PRESENCE);
+        let presence = self.pin.read() == Level::Low;
+        wait_until(start, RESET_PRESENCE + RESET_RECOVERY);
+
+        if self.pin.read() == Level::Low {
+            return Err(Error::BusShorted);
+        }
+
+        Ok(presence)
+    }
+
+    /// Writes a single bit.
+    pub fn write_bit(&mut self, bit: bool) {
+        let start = Instant::now();
+        self.pull_low();
+
+        if bit {
+            wait_until(start, WRITE_1_LOW);
+            self.release();
+            wait_until(start, WRITE_1_LOW + WRITE_1_RECOVERY);
+        } else {
+            wait_until(start, WRITE_0_LOW);
+            self.release();
+            wait_until(start, WRITE_0_LOW + WRITE_0_RECOVERY);
+        }
+    }
+
+    /// Reads a single bit.
+    pub fn read_bit(&mut self) -> bool {
+        let start = Instant::now();
+        self.pull_low();
+        wait_until(start, READ_LOW);
+        self.release();
+        wait_until(start, READ_LOW + READ_SAMPLE);
+        let bit = self.pin.read() == Level::High;
+        wait_until(start, READ_LOW + READ_SAMPLE + READ_RECOVERY);
+
+        bit
+    }
+
+    /// Writes a single byte, least-significant bit first.
+    pub fn write_byte(&mut self, byte: u8) {
+        for bit in 0..8 {
+            self.write_bit((byte >> bit) & 0x01 != 0);
+        }
+    }
+
+    /// Reads a single byte, least-significant bit first.
+    pub fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0u8, |byte, bit| byte | ((self.read_bit() as u8) << bit))
+    }
+
+    /// Writes the outgoing data contained in `buffer`.
+    pub fn write(&mut self, buffer: &[u8]) {
+        for byte in buffer {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Reads as many bytes as can fit in `buffer`.
+    pub fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Resets the bus, and selects a single device using the Match ROM command.
+    ///
+    /// Any function commands that follow are only processed by the device with
+    /// the specified ROM code.
+    ///
+    /// Returns `Err(`[`Error::NoPresence`]`)` if no devices are present.
+    ///
+    /// [`Error::NoPresence`]: enum.Error.html#variant.NoPresence
+    pub fn select(&mut self, rom: &Rom) -> Result<()> {
+        self.reset_presence()?;
+        self.write_byte(CMD_MATCH_ROM);
+        self.write(&rom.0);
+
+        Ok(())
+    }
+
+    /// Resets the bus, and addresses all devices using the Skip ROM command.
+    ///
+    /// Any function commands that follow are processed by all devices on the bus.
+    ///
+    /// Returns `Err(`[`Error::NoPresence`]`)` if no devices are present.
+    ///
+    /// [`Error::NoPresence`]: enum.Error.html#variant.NoPresence
+    pub fn skip(&mut self) -> Result<()> {
+        self.reset_presence()?;
+        self.write_byte(CMD_SKIP_ROM);
+
+        Ok(())
+    }
+
+    /// Reads the ROM code using the Read ROM command.
+    ///
+    /// The Read ROM command can only be used when there's a single device on the
+    /// bus. If multiple devices respond, the ROM code is corrupted, and `read_rom`
+    /// returns `Err(`[`Error::InvalidCrc`]`)`.
+    ///
+    /// [`Error::InvalidCrc`]: enum.Error.html#variant.InvalidCrc
+    pub fn read_rom(&mut self) -> Result<Rom> {
+        self.reset_presence()?;
+        self.write_byte(CMD_READ_ROM);
+
+        let mut bytes = [0u8; 8];
+        self.read(&mut bytes);
+
+        Rom::new(bytes)
+    }
+
+    /// Returns the ROM codes of all devices on the bus.
+    ///
+    /// `search` uses the Search ROM algorithm described in Maxim Integrated
+    /// application note 187. If no devices are present, an empty `Vec` is returned.
+    pub fn search(&mut self) -> Result<Vec<Rom>> {
+        self.search_command(CMD_SEARCH_ROM)
+    }
+
+    /// Returns the ROM codes of all devices with an active alarm condition.
+    ///
+    /// `search_alarms` works similarly to [`search`], but uses the Alarm Search
+    /// command, which only selects devices with an active alarm condition.
+    ///
+    /// [`search`]: #method.search
+    pub fn search_alarms(&mut self) -> Result<Vec<Rom>> {
+        self.search_command(CMD_ALARM_SEARCH)
+    }
+
+    fn search_command(&mut self, command: u8) -> Result<Vec<Rom>> {
+        let mut roms = Vec::new();
+        let mut bytes = [0u8; 8];
+        // Bit position (1-64) of the last discrepancy where the 0 branch was taken
+        let mut last_discrepancy = 0;
+
+        loop {
+            if !self.reset()? {
+                break;
+            }
+
+            self.write_byte(command);
+
+            let mut last_zero = 0;
+            for position in 1..=64 {
+                let index = (position - 1) / 8;
+                let mask = 1 << ((position - 1) % 8);
+
+                let id_bit = self.read_bit();
+                let cmp_id_bit = self.read_bit();
+
+                let direction = match (id_bit, cmp_id_bit) {
+                    // No (more) devices are participating in the search
+                    (true, true) => return Ok(roms),
+                    // All remaining devices have the same value for this bit
+                    (true, false) => true,
+                    (false, true) => false,
+                    // Discrepancy
+                    (false, false) => {
+                        let direction = if position < last_discrepancy {
+                            bytes[index] & mask != 0
+                        } else {
+                            position == last_discrepancy
+                        };
+
+                        if !direction {
+                            last_zero = position;
+                        }
+
+                        direction
+                    }
+                };
+
+                if direction {
+                    bytes[index] |= mask;
+                } else {
+                    bytes[index] &= !mask;
+                }
+
+                self.write_bit(direction);
+            }
+
+            roms.push(Rom::new(bytes)?);
+
+            last_discrepancy = last_zero;
+            if last_discrepancy == 0 {
+                break;
+            }
+        }
+
+        Ok(roms)
+    }
+
+    // Resets the bus, and returns an error if no devices are present
+    fn reset_presence(&mut self) -> Result<()> {
+        if self.reset()? {
+            Ok(())
+        } else {
+            Err(Error::NoPresence)
+        }
+    }
+
+    #[inline(always)]
+    fn pull_low(&mut self) {
+        self.pin.set_mode(Mode::Output);
+    }
+
+    #[inline(always)]
+    fn release(&mut self) {
+        self.pin.set_mode(Mode::Input);
+    }
+}
+
+// Busy-waits until the specified number of microseconds have elapsed since start
+#[inline(always)]
+fn wait_until(start: Instant, micros: u64) {
+    let duration = Duration::from_micros(micros);
+    while start.elapsed() < duration {}
+}
+
+#[inline(always)]
+fn delay_us(micros: u64) {
+    wait_until(Instant::now(), micros);
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{crc8, Error, OneWire, Result, Rom};
+
+// Supported family codes
+const FAMILY_DS18S20: u8 = 0x10;
+const FAMILY_DS1822: u8 = 0x22;
+const FAMILY_DS18B20: u8 = 0x28;
+const FAMILY_DS1825: u8 = 0x3B;
+
+// Function commands
+const CMD_CONVERT_T: u8 = 0x44;
+const CMD_WRITE_SCRATCHPAD: u8 = 0x4E;
+const CMD_READ_SCRATCHPAD: u8 = 0xBE;
+const CMD_COPY_SCRATCHPAD: u8 = 0x48;
+
+// Scratchpad layout
+const SCRATCHPAD_LEN: usize = 9;
+const SCRATCHPAD_TH: usize = 2;
+const SCRATCHPAD_TL: usize = 3;
+const SCRATCHPAD_CONFIG: usize = 4;
+const SCRATCHPAD_COUNT_REMAIN: usize = 6;
+const SCRATCHPAD_COUNT_PER_C: usize = 7;
+
+// Interval between completion checks during a temperature conversion
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// Maximum duration of an EEPROM write
+const EEPROM_WRITE_TIME: Duration = Duration::from_millis(10);
+
+/// Temperature conversion resolutions.
+///
+/// Higher resolutions take longer to convert. The DS18S20 always
+/// uses a 9-bit conversion, extended to a higher resolution by
+/// [`Ds18b20::read_temperature`].
+///
+/// [`Ds18b20::read_temperature`]: struct.Ds18b20.html#method.read_temperature
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Resolution {
+    /// 0.5 °C, 93.75 ms conversion time.
+    Bits9 = 0,
+    /// 0.25 °C, 187.5 ms conversion time.
+    Bits10 = 1,
+    /// 0.125 °C, 375 ms conversion time.
+    Bits11 = 2,
+    /// 0.0625 °C, 750 ms conversion time.
+    Bits12 = 3,
+}
+
+impl Resolution {
+    /// Returns the maximum temperature conversion time.
+    pub fn conversion_time(self) -> Duration {
+        Duration::from_micros(93_750 << (self as u64))
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Resolution::Bits9 => write!(f, "Bits9"),
+            Resolution::Bits10 => write!(f, "Bits10"),
+            Resolution::Bits11 => write!(f, "Bits11"),
+            Resolution::Bits12 => write!(f, "Bits12"),
+        }
+    }
+}
+
+/// Provides access to a DS18B20 family temperature sensor.
+///
+/// `Ds18b20` supports the DS18B20, DS18S20, DS1822 and DS1825 (including
+/// the MAX31850 thermocouple converter, which shares the DS1825's family code).
+/// The sensor requires an external power supply. Parasite-powered sensors
+/// aren't supported.
+///
+/// `Ds18b20` only stores the sensor's ROM code. Each method takes the
+/// [`OneWire`] bus the sensor is connected to.
+///
+/// [`OneWire`]: struct.OneWire.html
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Ds18b20 {
+    rom: Rom,
+}
+
+impl Ds18b20 {
+    /// Constructs a new `Ds18b20` for the sensor with the specified ROM code.
+    ///
+    /// Returns `Err(`[`Error::UnsupportedFamily`]`)` if the ROM code doesn't
+    /// belong to a supported temperature sensor.
+    ///
+    /// [`Error::UnsupportedFamily`]: enum.Error.html#variant.UnsupportedFamily
+    pub fn new(rom: Rom) -> Result<Ds18b20> {
+        if !Ds18b20::is_supported(rom.family()) {
+            return Err(Error::UnsupportedFamily(rom.family()));
+        }
+
+        Ok(Ds18b20 { rom })
+    }
+
+    /// Searches the bus, and returns all supported temperature sensors.
+    pub fn find_all(bus: &mut OneWire) -> Result<Vec<Ds18b20>> {
+        Ok(bus
+            .search()?
+            .into_iter()
+            .filter(|rom| Ds18b20::is_supported(rom.family()))
+            .map(|rom| Ds18b20 { rom })
+            .collect())
+    }
+
+    /// Starts a simultaneous temperature conversion on all sensors on the bus.
+    ///
+    /// Wait for at least the conversion time of the highest configured
+    /// [`Resolution`] before reading the temperature from each sensor
+    /// with [`read_temperature`].
+    ///
+    /// [`Resolution`]: enum.Resolution.html
+    /// [`read_temperature`]: #method.read_temperature
+    pub fn start_conversion_all(bus: &mut OneWire) -> Result<()> {
+        bus.skip()?;
+        bus.write_byte(CMD_CONVERT_T);
+
+        Ok(())
+    }
+
+    /// Returns the sensor's ROM code.
+    pub fn rom(&self) -> Rom {
+        self.rom
+    }
+
+    /// Starts a temperature conversion.
+    pub fn start_conversion(&self, bus: &mut OneWire) -> Result<()> {
+        bus.select(&self.rom)?;
+        bus.write_byte(CMD_CONVERT_T);
+
+        Ok(())
+    }
+
+    /// Blocks until the temperature conversion has completed.
+    ///
+    /// The sensor signals completion through its read time slots. If the
+    /// conversion hasn't completed within the maximum conversion time for the
+    /// current resolution, `wait_for_conversion` returns
+    /// `Err(`[`Error::TimedOut`]`)`.
+    ///
+    /// `wait_for_conversion` must be called directly after [`start_conversion`],
+    /// without any other commands in between.
+    ///
+    /// [`Error::TimedOut`]: enum.Error.html#variant.TimedOut
+    /// [`start_conversion`]: #method.start_conversion
+    pub fn wait_for_conversion(&self, bus: &mut OneWire, resolution: Resolution) -> Result<()> {
+        let start = Instant::now();
+        let timeout = resolution.conversion_time() + POLL_INTERVAL;
+
+        while !bus.read_bit() {
+            if start.elapsed() > timeout {
+                return Err(Error::TimedOut);
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        Ok(())
+    }
+
+    /// Starts a temperature conversion, waits until it completes, and returns
+    /// the temperature in degrees Celsius (°C).
+    pub fn measure_temperature(&self, bus: &mut OneWire) -> Result<f32> {
+        let resolution = self.resolution(bus)?;
+
+        self.start_conversion(bus)?;
+        self.wait_for_conversion(bus, resolution)?;
+
+        self.read_temperature(bus)
+    }
+
+    /// Returns the result of the last temperature conversion in degrees Celsius (°C).
+    ///
+    /// Until the first conversion completes, the sensor returns its power-on
+    /// reset value of 85 °C.
+    pub fn read_temperature(&self, bus: &mut OneWire) -> Result<f32> {
+        let scratchpad = self.read_scratchpad(bus)?;
+        let raw = i16::from_le_bytes([scratchpad[0], scratchpad[1]]);
+
+        if self.rom.family() == FAMILY_DS18S20 {
+            // 9-bit value in 0.5 °C units, extended using COUNT_REMAIN and COUNT_PER_C
+            let count_remain = f32::from(scratchpad[SCRATCHPAD_COUNT_REMAIN]);
+            let count_per_c = f32::from(scratchpad[SCRATCHPAD_COUNT_PER_C]);
+
+            if count_per_c == 0.0 {
+                return Ok(f32::from(raw) / 2.0);
+            }
+
+            Ok(f32::from(raw >> 1) - 0.25 + (count_per_c - count_remain) / count_per_c)
+        } else {
+            // Undefined low bits depend on the resolution
+            let undefined = 3 - ((scratchpad[SCRATCHPAD_CONFIG] >> 5) & 0x03);
+            let raw = raw & !((1 << undefined) - 1);
+
+            Ok(f32::from(raw) / 16.0)
+        }
+    }
+
+    /// Returns the configured temperature conversion resolution.
+    pub fn resolution(&self, bus: &mut OneWire) -> Result<Resolution> {
+        if self.rom.family() == FAMILY_DS18S20 {
+            return Ok(Resolution::Bits9);
+        }
+
+        let scratchpad = self.read_scratchpad(bus)?;
+
+        Ok(match (scratchpad[SCRATCHPAD_CONFIG] >> 5) & 0x03 {
+            0 => Resolution::Bits9,
+            1 => Resolution::Bits10,
+            2 => Resolution::Bits11,
+            _ => Resolution::Bits12,
+        })
+    }
+
+    /// Sets the temperature conversion resolution.
+    ///
+    /// The new resolution is stored in the sensor's volatile scratchpad. Call
+    /// [`save`] to keep the resolution after a power cycle.
+    ///
+    /// The DS18S20 doesn't support a configurable resolution. Any value
+    /// other than [`Bits9`] returns `Err(`[`Error::UnsupportedFamily`]`)`.
+    ///
+    /// [`save`]: #method.save
+    /// [`Bits9`]: enum.Resolution.html#variant.Bits9
+    /// [`Error::UnsupportedFamily`]: enum.Error.html#variant.UnsupportedFamily
+    pub fn set_resolution(&self, bus: &mut OneWire, resolution: Resolution) -> Result<()> {
+        if self.rom.family() == FAMILY_DS18S20 {
+            return if resolution == Resolution::Bits9 {
+                Ok(())
+            } else {
+                Err(Error::UnsupportedFamily(self.rom.family()))
+            };
+        }
+
+        // Keep the current alarm thresholds
+        let scratchpad = self.read_scratchpad(bus)?;
+
+        bus.select(&self.rom)?;
+        bus.write(&[
+            CMD_WRITE_SCRATCHPAD,
+            scratchpad[SCRATCHPAD_TH],
+            scratchpad[SCRATCHPAD_TL],
+            ((resolution as u8) << 5) | 0x1F,
+        ]);
+
+        Ok(())
+    }
+
+    /// Copies the alarm thresholds and resolution from the scratchpad to the
+    /// sensor's EEPROM, so they're restored after a power cycle.
+    pub fn save(&self, bus: &mut OneWire) -> Result<()> {
+        bus.select(&self.rom)?;
+        bus.write_byte(CMD_COPY_SCRATCHPAD);
+        thread::sleep(EEPROM_WRITE_TIME);
+
+        Ok(())
+    }
+
+    /// Reads and verifies the sensor's 9-byte scratchpad.
+    ///
+    /// Returns `Err(`[`Error::InvalidCrc`]`)` if the scratchpad was corrupted
+    /// during the transfer.
+    ///
+    /// [`Error::InvalidCrc`]: enum.Error.html#variant.InvalidCrc
+    pub fn read_scratchpad(&self, bus: &mut OneWire) -> Result<[u8; 9]> {
+        bus.select(&self.rom)?;
+        bus.write_byte(CMD_READ_SCRATCHPAD);
+
+        let mut scratchpad = [0u8; SCRATCHPAD_LEN];
+        bus.read(&mut scratchpad);
+
+        // An all-zero scratchpad has a valid CRC, but means nobody responded
+        if crc8(&scratchpad) != 0 || scratchpad.iter().all(|byte| *byte == 0) {
+            return Err(Error::InvalidCrc);
+        }
+
+        Ok(scratchpad)
+    }
+
+    fn is_supported(family: u8) -> bool {
+        matches!(
+            family,
+            FAMILY_DS18S20 | FAMILY_DS1822 | FAMILY_DS18B20 | FAMILY_DS1825
+        )
+    }
+}
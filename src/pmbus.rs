@@ -0,0 +1,447 @@
+//! Interface for PMBus power management devices.
+//!
+//! PMBus is a command protocol built on top of SMBus, used by power supplies,
+//! voltage regulators, battery chargers and other power conversion devices.
+//! [`Pmbus`] wraps an [`I2c`] instance, and offers typed access to the standard
+//! PMBus commands, including conversion of the LINEAR11, LINEAR16 and DIRECT
+//! numeric formats, and decoding of the status registers.
+//!
+//! ## Numeric formats
+//!
+//! Most telemetry commands (`READ_VIN`, `READ_IOUT`, `READ_TEMPERATURE_1`, ...)
+//! use the LINEAR11 format. Output voltage commands use the format specified by
+//! the `VOUT_MODE` register, which is either LINEAR16 or DIRECT. Some devices use
+//! the DIRECT format for their telemetry as well, in which case the coefficients
+//! listed in the datasheet can be configured with [`set_coefficients`].
+//!
+//! The conversion functions are also available separately, for use with
+//! manufacturer-specific commands.
+//!
+//! ## Packet Error Checking
+//!
+//! If both the device and the underlying drivers support SMBus Packet Error
+//! Checking, [`Pmbus::new`] automatically enables it based on the contents of
+//! the device's `CAPABILITY` register.
+//!
+//! [`Pmbus`]: struct.Pmbus.html
+//! [`Pmbus::new`]: struct.Pmbus.html#method.new
+//! [`I2c`]: ../i2c/struct.I2c.html
+//! [`set_coefficients`]: struct.Pmbus.html#method.set_coefficients
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::result;
+
+use crate::i2c::{self, I2c};
+
+mod format;
+mod status;
+
+pub use self::format::{
+    decode_linear11, decode_linear16, encode_linear11, encode_linear16, DirectCoefficients,
+};
+pub use self::status::{
+    StatusCml, StatusCmlBit, StatusInput, StatusInputBit, StatusIout, StatusIoutBit,
+    StatusTemperature, StatusTemperatureBit, StatusVout, StatusVoutBit, StatusWord, StatusWordBit,
+};
+
+// CAPABILITY register flags
+const CAPABILITY_PEC: u8 = 0x80;
+
+/// Errors that can occur when accessing a PMBus device.
+#[derive(Debug)]
+pub enum Error {
+    /// I2C error.
+    I2c(i2c::Error),
+    /// Unsupported numeric format.
+    ///
+    /// The device uses a numeric format that can't be converted, or the
+    /// DIRECT format without any configured coefficients.
+    UnsupportedFormat,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::I2c(ref err) => write!(f, "I2C error: {}", err),
+            Error::UnsupportedFormat => write!(f, "Unsupported numeric format"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<i2c::Error> for Error {
+    fn from(err: i2c::Error) -> Error {
+        Error::I2c(err)
+    }
+}
+
+/// Result type returned from methods that can have `pmbus::Error`s.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Standard PMBus commands.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[repr(u8)]
+pub enum Command {
+    /// `PAGE`
+    Page = 0x00,
+    /// `OPERATION`
+    Operation = 0x01,
+    /// `ON_OFF_CONFIG`
+    OnOffConfig = 0x02,
+    /// `CLEAR_FAULTS`
+    ClearFaults = 0x03,
+    /// `CAPABILITY`
+    Capability = 0x19,
+    /// `VOUT_MODE`
+    VoutMode = 0x20,
+    /// `VOUT_COMMAND`
+    VoutCommand = 0x21,
+    /// `VOUT_MAX`
+    VoutMax = 0x24,
+    /// `VOUT_MARGIN_HIGH`
+    VoutMarginHigh = 0x25,
+    /// `VOUT_MARGIN_LOW`
+    VoutMarginLow = 0x26,
+    /// `VOUT_OV_FAULT_LIMIT`
+    VoutOvFaultLimit = 0x40,
+    /// `VOUT_UV_FAULT_LIMIT`
+    VoutUvFaultLimit = 0x44,
+    /// `IOUT_OC_FAULT_LIMIT`
+    IoutOcFaultLimit = 0x46,
+    /// `OT_FAULT_LIMIT`
+    OtFaultLimit = 0x4F,
+    /// `OT_WARN_LIMIT`
+    OtWarnLimit = 0x51,
+    /// `VIN_OV_FAULT_LIMIT`
+    VinOvFaultLimit = 0x55,
+    /// `VIN_UV_FAULT_LIMIT`
+    VinUvFaultLimit = 0x59,
+    /// `STATUS_BYTE`
+    StatusByte = 0x78,
+    /// `STATUS_WORD`
+    StatusWord = 0x79,
+    /// `STATUS_VOUT`
+    StatusVout = 0x7A,
+    /// `STATUS_IOUT`
+    StatusIout = 0x7B,
+    /// `STATUS_INPUT`
+    StatusInput = 0x7C,
+    /// `STATUS_TEMPERATURE`
+    StatusTemperature = 0x7D,
+    /// `STATUS_CML`
+    StatusCml = 0x7E,
+    /// `READ_VIN`
+    ReadVin = 0x88,
+    /// `READ_IIN`
+    ReadIin = 0x89,
+    /// `READ_VOUT`
+    ReadVout = 0x8B,
+    /// `READ_IOUT`
+    ReadIout = 0x8C,
+    /// `READ_TEMPERATURE_1`
+    ReadTemperature1 = 0x8D,
+    /// `READ_TEMPERATURE_2`
+    ReadTemperature2 = 0x8E,
+    /// `READ_TEMPERATURE_3`
+    ReadTemperature3 = 0x8F,
+    /// `READ_FAN_SPEED_1`
+    ReadFanSpeed1 = 0x90,
+    /// `READ_DUTY_CYCLE`
+    ReadDutyCycle = 0x94,
+    /// `READ_FREQUENCY`
+    ReadFrequency = 0x95,
+    /// `READ_POUT`
+    ReadPout = 0x96,
+    /// `READ_PIN`
+    ReadPin = 0x97,
+    /// `PMBUS_REVISION`
+    PmbusRevision = 0x98,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Output voltage data formats, as specified by the `VOUT_MODE` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum VoutMode {
+    /// LINEAR16 format with the included exponent.
+    Linear(i8),
+    /// VID format with the included VID code type.
+    Vid(u8),
+    /// DIRECT format.
+    Direct,
+    /// IEEE 754 half precision format.
+    Ieee754,
+}
+
+impl VoutMode {
+    /// Decodes the contents of the `VOUT_MODE` register.
+    ///
+    /// Returns `Err(`[`Error::UnsupportedFormat`]`)` for reserved modes.
+    ///
+    /// [`Error::UnsupportedFormat`]: enum.Error.html#variant.UnsupportedFormat
+    pub fn from_bits(bits: u8) -> Result<VoutMode> {
+        // Lower 5 bits contain a parameter, sign-extended for the exponent
+        let parameter = bits & 0x1F;
+
+        match bits >> 5 {
+            0b000 => Ok(VoutMode::Linear(((parameter << 3) as i8) >> 3)),
+            0b001 => Ok(VoutMode::Vid(parameter)),
+            0b010 => Ok(VoutMode::Direct),
+            0b011 => Ok(VoutMode::Ieee754),
+            _ => Err(Error::UnsupportedFormat),
+        }
+    }
+}
+
+/// Provides access to a PMBus device.
+///
+/// More information on the supported numeric formats and Packet Error Checking
+/// can be found [here].
+///
+/// [here]: index.html
+#[derive(Debug)]
+pub struct Pmbus {
+    i2c: I2c,
+    pec: bool,
+    vout_mode: Option<VoutMode>,
+    coefficients: HashMap<Command, DirectCoefficients>,
+}
+
+impl Pmbus {
+    /// Constructs a new `Pmbus` for the device at the specified 7-bit slave `address`.
+    ///
+    /// `new` reads the device's `CAPABILITY` register, and enables SMBus Packet
+    /// Error Checking if it's supported by both the device and the underlying
+    /// drivers. Devices that don't implement `CAPABILITY` are accessed without PEC.
+    pub fn new(mut i2c: I2c, address: u16) -> Result<Pmbus> {
+        i2c.set_slave_address(address)?;
+
+        let pec = i2c.capabilities().smbus_pec()
+            && matches!(
+                i2c.smbus_read_byte(Command::Capability as u8),
+                Ok(capability) if capability & CAPABILITY_PEC > 0
+            );
+
+        i2c.set_smbus_pec(pec)?;
+
+        Ok(Pmbus {
+            i2c,
+            pec,
+            vout_mode: None,
+            coefficients: HashMap::new(),
+        })
+    }
+
+    /// Returns a reference to the underlying [`I2c`].
+    ///
+    /// [`I2c`]: ../i2c/struct.I2c.html
+    pub fn i2c(&self) -> &I2c {
+        &self.i2c
+    }
+
+    /// Consumes the `Pmbus`, and returns the underlying [`I2c`].
+    ///
+    /// [`I2c`]: ../i2c/struct.I2c.html
+    pub fn into_inner(self) -> I2c {
+        self.i2c
+    }
+
+    /// Returns `true` if SMBus Packet Error Checking is enabled.
+    pub fn pec(&self) -> bool {
+        self.pec
+    }
+
+    /// Configures the DIRECT format coefficients used to convert values for `command`.
+    ///
+    /// Telemetry commands use the LINEAR11 format, unless coefficients
+    /// have been configured. The output voltage commands use the DIRECT format
+    /// only when specified by `VOUT_MODE`.
+    pub fn set_coefficients(&mut self, command: Command, coefficients: DirectCoefficients) {
+        self.coefficients.insert(command, coefficients);
+    }
+
+    /// Selects the active page on devices with multiple outputs.
+    pub fn set_page(&mut self, page: u8) -> Result<()> {
+        self.i2c.smbus_write_byte(Command::Page as u8, page)?;
+
+        // VOUT_MODE may differ between pages
+        self.vout_mode = None;
+
+        Ok(())
+    }
+
+    /// Clears all fault and warning status bits.
+    pub fn clear_faults(&self) -> Result<()> {
+        Ok(self.i2c.smbus_send_byte(Command::ClearFaults as u8)?)
+    }
+
+    /// Reads an 8-bit value using the specified `command`.
+    pub fn read_byte(&self, command: u8) -> Result<u8> {
+        Ok(self.i2c.smbus_read_byte(command)?)
+    }
+
+    /// Writes an 8-bit value using the specified `command`.
+    pub fn write_byte(&self, command: u8, value: u8) -> Result<()> {
+        Ok(self.i2c.smbus_write_byte(command, value)?)
+    }
+
+    /// Reads a 16-bit value using the specified `command`.
+    pub fn read_word(&self, command: u8) -> Result<u16> {
+        Ok(self.i2c.smbus_read_word(command)?)
+    }
+
+    /// Writes a 16-bit value using the specified `command`.
+    pub fn write_word(&self, command: u8, value: u16) -> Result<()> {
+        Ok(self.i2c.smbus_write_word(command, value)?)
+    }
+
+    /// Returns the output voltage data format.
+    ///
+    /// The contents of `VOUT_MODE` are cached until the page is changed.
+    pub fn vout_mode(&mut self) -> Result<VoutMode> {
+        if let Some(vout_mode) = self.vout_mode {
+            return Ok(vout_mode);
+        }
+
+        let vout_mode = VoutMode::from_bits(self.read_byte(Command::VoutMode as u8)?)?;
+        self.vout_mode = Some(vout_mode);
+
+        Ok(vout_mode)
+    }
+
+    /// Returns the input voltage in volts (V).
+    pub fn read_vin(&self) -> Result<f32> {
+        self.read_value(Command::ReadVin)
+    }
+
+    /// Returns the input current in amperes (A).
+    pub fn read_iin(&self) -> Result<f32> {
+        self.read_value(Command::ReadIin)
+    }
+
+    /// Returns the output voltage in volts (V).
+    pub fn read_vout(&mut self) -> Result<f32> {
+        self.read_vout_value(Command::ReadVout)
+    }
+
+    /// Returns the output current in amperes (A).
+    pub fn read_iout(&self) -> Result<f32> {
+        self.read_value(Command::ReadIout)
+    }
+
+    /// Returns the temperature reported by the first sensor in degrees Celsius (°C).
+    pub fn read_temperature_1(&self) -> Result<f32> {
+        self.read_value(Command::ReadTemperature1)
+    }
+
+    /// Returns the temperature reported by the second sensor in degrees Celsius (°C).
+    pub fn read_temperature_2(&self) -> Result<f32> {
+        self.read_value(Command::ReadTemperature2)
+    }
+
+    /// Returns the temperature reported by the third sensor in degrees Celsius (°C).
+    pub fn read_temperature_3(&self) -> Result<f32> {
+        self.read_value(Command::ReadTemperature3)
+    }
+
+    /// Returns the speed of the first fan in revolutions per minute (RPM).
+    pub fn read_fan_speed_1(&self) -> Result<f32> {
+        self.read_value(Command::ReadFanSpeed1)
+    }
+
+    /// Returns the output power in watts (W).
+    pub fn read_pout(&self) -> Result<f32> {
+        self.read_value(Command::ReadPout)
+    }
+
+    /// Returns the input power in watts (W).
+    pub fn read_pin(&self) -> Result<f32> {
+        self.read_value(Command::ReadPin)
+    }
+
+    /// Returns the nominal output voltage in volts (V).
+    pub fn vout_command(&mut self) -> Result<f32> {
+        self.read_vout_value(Command::VoutCommand)
+    }
+
+    /// Sets the nominal output voltage in volts (V).
+    pub fn set_vout_command(&mut self, volts: f32) -> Result<()> {
+        let value = match self.vout_mode()? {
+            VoutMode::Linear(exponent) => encode_linear16(volts, exponent),
+            VoutMode::Direct => self.coefficients(Command::VoutCommand)?.encode(volts),
+            _ => return Err(Error::UnsupportedFormat),
+        };
+
+        self.write_word(Command::VoutCommand as u8, value)
+    }
+
+    /// Reads the `STATUS_WORD` register.
+    pub fn status_word(&self) -> Result<StatusWord> {
+        Ok(StatusWord::from(self.read_word(Command::StatusWord as u8)?))
+    }
+
+    /// Reads the `STATUS_VOUT` register.
+    pub fn status_vout(&self) -> Result<StatusVout> {
+        Ok(StatusVout::from(self.read_byte(Command::StatusVout as u8)?))
+    }
+
+    /// Reads the `STATUS_IOUT` register.
+    pub fn status_iout(&self) -> Result<StatusIout> {
+        Ok(StatusIout::from(self.read_byte(Command::StatusIout as u8)?))
+    }
+
+    /// Reads the `STATUS_INPUT` register.
+    pub fn status_input(&self) -> Result<StatusInput> {
+        Ok(StatusInput::from(
+            self.read_byte(Command::StatusInput as u8)?,
+        ))
+    }
+
+    /// Reads the `STATUS_TEMPERATURE` register.
+    pub fn status_temperature(&self) -> Result<StatusTemperature> {
+        Ok(StatusTemperature::from(
+            self.read_byte(Command::StatusTemperature as u8)?,
+        ))
+    }
+
+    /// Reads the `STATUS_CML` register.
+    pub fn status_cml(&self) -> Result<StatusCml> {
+        Ok(StatusCml::from(self.read_byte(Command::StatusCml as u8)?))
+    }
+
+    // Reads a value in the LINEAR11 format, or DIRECT if coefficients are configured
+    fn read_value(&self, command: Command) -> Result<f32> {
+        let value = self.read_word(command as u8)?;
+
+        Ok(match self.coefficients.get(&command) {
+            Some(coefficients) => coefficients.decode(value),
+            None => decode_linear11(value),
+        })
+    }
+
+    // Reads a value in the format specified by VOUT_MODE
+    fn read_vout_value(&mut self, command: Command) -> Result<f32> {
+        let vout_mode = self.vout_mode()?;
+        let value = self.read_word(command as u8)?;
+
+        match vout_mode {
+            VoutMode::Linear(exponent) => Ok(decode_linear16(value, exponent)),
+            VoutMode::Direct => Ok(self.coefficients(command)?.decode(value)),
+            _ => Err(Error::UnsupportedFormat),
+        }
+    }
+
+    fn coefficients(&self, command: Command) -> Result<DirectCoefficients> {
+        self.coefficients
+            .get(&command)
+            .copied()
+            .ok_or(Error::UnsupportedFormat)
+    }
+}
@@ -0,0 +1,86 @@
+/// Decodes a LINEAR11 value.
+///
+/// LINEAR11 values consist of a 5-bit two's complement exponent `N` in the
+/// upper bits, followed by an 11-bit two's complement mantissa `Y`. The
+/// decoded value equals `Y · 2^N`.
+pub fn decode_linear11(value: u16) -> f32 {
+    // Sign-extend both fields by shifting them into the most significant bits
+    let exponent = ((value as i16) >> 11) as i32;
+    let mantissa = (((value << 5) as i16) >> 5) as f32;
+
+    mantissa * 2f32.powi(exponent)
+}
+
+/// Encodes a value in the LINEAR11 format.
+///
+/// The exponent is selected to retain as much precision as possible. Values
+/// that exceed the LINEAR11 range are clamped.
+pub fn encode_linear11(value: f32) -> u16 {
+    // Find the smallest exponent that fits the mantissa in 11 bits
+    let mut exponent: i32 = -16;
+    let mut mantissa = (value * 2f32.powi(-exponent)).round();
+    while !(-1024.0..=1023.0).contains(&mantissa) && exponent < 15 {
+        exponent += 1;
+        mantissa = (value * 2f32.powi(-exponent)).round();
+    }
+
+    let mantissa = mantissa.clamp(-1024.0, 1023.0) as i16;
+
+    (((exponent as u16) & 0x1F) << 11) | ((mantissa as u16) & 0x07FF)
+}
+
+/// Decodes a LINEAR16 value.
+///
+/// LINEAR16 values consist of a 16-bit unsigned mantissa. The exponent is
+/// stored separately in the `VOUT_MODE` register. The decoded value equals
+/// `mantissa · 2^exponent`.
+pub fn decode_linear16(value: u16, exponent: i8) -> f32 {
+    f32::from(value) * 2f32.powi(i32::from(exponent))
+}
+
+/// Encodes a value in the LINEAR16 format using the specified `exponent`.
+///
+/// Values that exceed the LINEAR16 range are clamped.
+pub fn encode_linear16(value: f32, exponent: i8) -> u16 {
+    (value * 2f32.powi(-i32::from(exponent)))
+        .round()
+        .clamp(0.0, f32::from(u16::MAX)) as u16
+}
+
+/// Coefficients used to convert values in the DIRECT format.
+///
+/// The coefficients are device-specific, and are typically listed in the
+/// datasheet for each command. Values are converted using
+/// `X = (Y · 10^-R - b) / m`, where `Y` is the two's complement register value.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct DirectCoefficients {
+    /// Slope coefficient.
+    pub m: i16,
+    /// Offset coefficient.
+    pub b: i16,
+    /// Exponent coefficient.
+    pub r: i8,
+}
+
+impl DirectCoefficients {
+    /// Constructs a new `DirectCoefficients`.
+    pub fn new(m: i16, b: i16, r: i8) -> DirectCoefficients {
+        DirectCoefficients { m, b, r }
+    }
+
+    /// Decodes a DIRECT value.
+    pub fn decode(&self, value: u16) -> f32 {
+        let y = f32::from(value as i16);
+
+        (y * 10f32.powi(-i32::from(self.r)) - f32::from(self.b)) / f32::from(self.m)
+    }
+
+    /// Encodes a value in the DIRECT format.
+    ///
+    /// Values that exceed the DIRECT range are clamped.
+    pub fn encode(&self, value: f32) -> u16 {
+        ((f32::from(self.m) * value + f32::from(self.b)) * 10f32.powi(i32::from(self.r)))
+            .round()
+            .clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16 as u16
+    }
+}
@@ -0,0 +1,197 @@
+// Implements the shared methods for a status register type
+macro_rules! status_register {
+    ($name:ident, $bit:ident, $ty:ty) => {
+        impl $name {
+            /// Returns the raw register value.
+            pub fn bits(self) -> $ty {
+                self.0
+            }
+
+            /// Returns `true` if `bit` is set.
+            pub fn contains(self, bit: $bit) -> bool {
+                (self.0 >> (bit as u8)) & 0x01 != 0
+            }
+
+            /// Returns `true` if none of the status bits are set.
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl From<$ty> for $name {
+            fn from(bits: $ty) -> $name {
+                $name(bits)
+            }
+        }
+    };
+}
+
+/// Status bits reported by `STATUS_WORD`.
+///
+/// The lower 8 bits match the contents of `STATUS_BYTE`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusWordBit {
+    /// A fault or warning not listed in the other bits.
+    NoneOfTheAbove = 0,
+    /// A communication, memory or logic fault.
+    Cml = 1,
+    /// A temperature fault or warning.
+    Temperature = 2,
+    /// An input undervoltage fault.
+    VinUvFault = 3,
+    /// An output overcurrent fault.
+    IoutOcFault = 4,
+    /// An output overvoltage fault.
+    VoutOvFault = 5,
+    /// The unit isn't providing power to the output.
+    Off = 6,
+    /// The device was busy, and unable to respond.
+    Busy = 7,
+    /// Unknown fault or warning.
+    Unknown = 8,
+    /// A fault or warning not listed in the other bits of `STATUS_WORD`.
+    Other = 9,
+    /// A fan fault or warning.
+    Fans = 10,
+    /// The POWER_GOOD signal is negated.
+    PowerGoodNegated = 11,
+    /// A manufacturer-specific fault or warning.
+    Mfr = 12,
+    /// An input voltage, current or power fault or warning.
+    Input = 13,
+    /// An output current or power fault or warning.
+    IoutPout = 14,
+    /// An output voltage fault or warning.
+    Vout = 15,
+}
+
+/// Contents of the `STATUS_WORD` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusWord(u16);
+
+status_register!(StatusWord, StatusWordBit, u16);
+
+/// Status bits reported by `STATUS_VOUT`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusVoutBit {
+    /// Power-on tracking error.
+    TrackingError = 0,
+    /// TOFF_MAX warning.
+    ToffMaxWarning = 1,
+    /// TON_MAX fault.
+    TonMaxFault = 2,
+    /// VOUT_MAX or VOUT_MIN warning.
+    VoutMaxMinWarning = 3,
+    /// Output undervoltage fault.
+    VoutUvFault = 4,
+    /// Output undervoltage warning.
+    VoutUvWarning = 5,
+    /// Output overvoltage warning.
+    VoutOvWarning = 6,
+    /// Output overvoltage fault.
+    VoutOvFault = 7,
+}
+
+/// Contents of the `STATUS_VOUT` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusVout(u8);
+
+status_register!(StatusVout, StatusVoutBit, u8);
+
+/// Status bits reported by `STATUS_IOUT`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusIoutBit {
+    /// Output overpower warning.
+    PoutOpWarning = 0,
+    /// Output overpower fault.
+    PoutOpFault = 1,
+    /// The unit is operating in power limiting mode.
+    PowerLimiting = 2,
+    /// Current share fault.
+    CurrentShareFault = 3,
+    /// Output undercurrent fault.
+    IoutUcFault = 4,
+    /// Output overcurrent warning.
+    IoutOcWarning = 5,
+    /// Output overcurrent and low voltage fault.
+    IoutOcLvFault = 6,
+    /// Output overcurrent fault.
+    IoutOcFault = 7,
+}
+
+/// Contents of the `STATUS_IOUT` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusIout(u8);
+
+status_register!(StatusIout, StatusIoutBit, u8);
+
+/// Status bits reported by `STATUS_INPUT`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusInputBit {
+    /// Input overpower warning.
+    PinOpWarning = 0,
+    /// Input overcurrent warning.
+    IinOcWarning = 1,
+    /// Input overcurrent fault.
+    IinOcFault = 2,
+    /// The unit is off because of insufficient input voltage.
+    UnitOffLowInput = 3,
+    /// Input undervoltage fault.
+    VinUvFault = 4,
+    /// Input undervoltage warning.
+    VinUvWarning = 5,
+    /// Input overvoltage warning.
+    VinOvWarning = 6,
+    /// Input overvoltage fault.
+    VinOvFault = 7,
+}
+
+/// Contents of the `STATUS_INPUT` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusInput(u8);
+
+status_register!(StatusInput, StatusInputBit, u8);
+
+/// Status bits reported by `STATUS_TEMPERATURE`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusTemperatureBit {
+    /// Undertemperature fault.
+    UtFault = 4,
+    /// Undertemperature warning.
+    UtWarning = 5,
+    /// Overtemperature warning.
+    OtWarning = 6,
+    /// Overtemperature fault.
+    OtFault = 7,
+}
+
+/// Contents of the `STATUS_TEMPERATURE` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusTemperature(u8);
+
+status_register!(StatusTemperature, StatusTemperatureBit, u8);
+
+/// Status bits reported by `STATUS_CML`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum StatusCmlBit {
+    /// Other memory or logic fault.
+    OtherMemoryLogicFault = 0,
+    /// Other communication fault.
+    OtherCommunicationFault = 1,
+    /// Processor fault.
+    ProcessorFault = 3,
+    /// Memory fault.
+    MemoryFault = 4,
+    /// Packet Error Check failed.
+    PecFailed = 5,
+    /// Invalid or unsupported data received.
+    InvalidData = 6,
+    /// Invalid or unsupported command received.
+    InvalidCommand = 7,
+}
+
+/// Contents of the `STATUS_CML` register.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct StatusCml(u8);
+
+status_register!(StatusCml, StatusCmlBit, u8);
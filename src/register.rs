@@ -0,0 +1,417 @@
+//! Register-based access to I2C and SPI slave devices.
+//!
+//! Most sensors and other peripheral ICs expose their functionality through
+//! a set of 8-bit registers. [`RegisterDevice`] wraps an [`I2c`], [`I2cDevice`]
+//! or [`Spi`] instance, and offers typed multi-byte reads and writes,
+//! bit-field read-modify-write operations, burst transfers and caching of
+//! write-only registers.
+//!
+//! ## Register access conventions
+//!
+//! On the I2C bus, a register is read by writing the register address,
+//! followed by a repeated START and a read operation. Registers are written by
+//! sending the register address followed by the data in a single write operation.
+//!
+//! On the SPI bus, the register address is sent as the first byte, followed by
+//! the incoming or outgoing data, while Slave Select stays active. Many devices use
+//! the most significant bit of the address byte to differentiate between reads and
+//! writes. [`set_read_flag`] and [`set_write_flag`] configure which bits are added
+//! to the register address.
+//!
+//! Some devices only automatically increment the register address during burst
+//! transfers when a specific bit is set in the address byte, which can be configured
+//! with [`set_auto_increment_flag`].
+//!
+//! [`RegisterDevice`]: struct.RegisterDevice.html
+//! [`I2c`]: ../i2c/struct.I2c.html
+//! [`I2cDevice`]: ../i2c/struct.I2cDevice.html
+//! [`Spi`]: ../spi/struct.Spi.html
+//! [`set_read_flag`]: struct.RegisterDevice.html#method.set_read_flag
+//! [`set_write_flag`]: struct.RegisterDevice.html#method.set_write_flag
+//! [`set_auto_increment_flag`]: struct.RegisterDevice.html#method.set_auto_increment_flag
+
+use std::collections::HashMap;
+use std::fmt;
+use std::result;
+
+use crate::i2c::{self, I2c, I2cDevice};
+use crate::spi::{self, Segment, Spi};
+
+// Maximum number of bytes transferred by I2c::block_write
+const I2C_BLOCK_MAX: usize = 32;
+
+/// Byte order used for multi-byte register values.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ByteOrder {
+    /// The most significant byte is stored in the lowest register address.
+    BigEndian,
+    /// The least significant byte is stored in the lowest register address.
+    LittleEndian,
+}
+
+impl fmt::Display for ByteOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ByteOrder::BigEndian => write!(f, "BigEndian"),
+            ByteOrder::LittleEndian => write!(f, "LittleEndian"),
+        }
+    }
+}
+
+/// Reads and writes consecutive registers on a slave device.
+///
+/// `RegisterBus` is implemented for [`I2c`], [`I2cDevice`] and [`Spi`]. The
+/// `register` argument already includes any flags configured on [`RegisterDevice`].
+///
+/// [`I2c`]: ../i2c/struct.I2c.html
+/// [`I2cDevice`]: ../i2c/struct.I2cDevice.html
+/// [`Spi`]: ../spi/struct.Spi.html
+/// [`RegisterDevice`]: struct.RegisterDevice.html
+pub trait RegisterBus {
+    /// Error type returned by the underlying bus.
+    type Error;
+
+    /// Default value for [`RegisterDevice::read_flag`].
+    ///
+    /// [`RegisterDevice::read_flag`]: struct.RegisterDevice.html#method.read_flag
+    const READ_FLAG: u8 = 0;
+
+    /// Reads as many consecutive registers as can fit in `buffer`, starting at `register`.
+    fn read_registers(
+        &mut self,
+        register: u8,
+        buffer: &mut [u8],
+    ) -> result::Result<(), Self::Error>;
+
+    /// Writes the contents of `buffer` to consecutive registers, starting at `register`.
+    fn write_registers(&mut self, register: u8, buffer: &[u8]) -> result::Result<(), Self::Error>;
+}
+
+impl RegisterBus for I2c {
+    type Error = i2c::Error;
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> i2c::Result<()> {
+        self.write_read(&[register], buffer)
+    }
+
+    fn write_registers(&mut self, register: u8, buffer: &[u8]) -> i2c::Result<()> {
+        if buffer.len() <= I2C_BLOCK_MAX {
+            self.block_write(register, buffer)
+        } else {
+            let mut data = Vec::with_capacity(buffer.len() + 1);
+            data.push(register);
+            data.extend_from_slice(buffer);
+
+            self.write(&data).map(|_| ())
+        }
+    }
+}
+
+impl RegisterBus for I2cDevice {
+    type Error = i2c::Error;
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> i2c::Result<()> {
+        self.access(|i2c| i2c.read_registers(register, buffer))
+    }
+
+    fn write_registers(&mut self, register: u8, buffer: &[u8]) -> i2c::Result<()> {
+        self.access(|i2c| i2c.write_registers(register, buffer))
+    }
+}
+
+impl RegisterBus for Spi {
+    type Error = spi::Error;
+
+    const READ_FLAG: u8 = 0x80;
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> spi::Result<()> {
+        let address = [register];
+
+        self.transfer_segments(&[Segment::with_write(&address), Segment::with_read(buffer)])
+    }
+
+    fn write_registers(&mut self, register: u8, buffer: &[u8]) -> spi::Result<()> {
+        let address = [register];
+
+        self.transfer_segments(&[Segment::with_write(&address), Segment::with_write(buffer)])
+    }
+}
+
+/// Provides register-based access to a slave device.
+///
+/// More information on the supported register access conventions can be found [here].
+///
+/// [here]: index.html
+pub struct RegisterDevice<B: RegisterBus> {
+    bus: B,
+    byte_order: ByteOrder,
+    read_flag: u8,
+    write_flag: u8,
+    auto_increment_flag: u8,
+    // Last written value for each write-only register
+    cache: HashMap<u8, u8>,
+}
+
+impl<B: RegisterBus> RegisterDevice<B> {
+    /// Constructs a new `RegisterDevice`.
+    ///
+    /// For `I2c`, the slave address should be configured before or after
+    /// constructing the `RegisterDevice`, using [`bus_mut`].
+    ///
+    /// By default, `byte_order` is set to [`BigEndian`], `write_flag` and
+    /// `auto_increment_flag` are set to `0x00`, and `read_flag` is set to `0x80`
+    /// for SPI and `0x00` for I2C.
+    ///
+    /// [`bus_mut`]: #method.bus_mut
+    /// [`BigEndian`]: enum.ByteOrder.html#variant.BigEndian
+    pub fn new(bus: B) -> RegisterDevice<B> {
+        RegisterDevice {
+            bus,
+            byte_order: ByteOrder::BigEndian,
+            read_flag: B::READ_FLAG,
+            write_flag: 0,
+            auto_increment_flag: 0,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying bus.
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
+
+    /// Returns a mutable reference to the underlying bus.
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    /// Consumes the `RegisterDevice`, and returns the underlying bus.
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Returns the byte order used for multi-byte register values.
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Sets the byte order used for multi-byte register values.
+    ///
+    /// By default, `byte_order` is set to [`BigEndian`].
+    ///
+    /// [`BigEndian`]: enum.ByteOrder.html#variant.BigEndian
+    pub fn set_byte_order(&mut self, byte_order: ByteOrder) {
+        self.byte_order = byte_order;
+    }
+
+    /// Returns the bits added to the register address for read operations.
+    pub fn read_flag(&self) -> u8 {
+        self.read_flag
+    }
+
+    /// Sets the bits added to the register address for read operations.
+    ///
+    /// By default, `read_flag` is set to `0x80` for SPI and `0x00` for I2C.
+    pub fn set_read_flag(&mut self, read_flag: u8) {
+        self.read_flag = read_flag;
+    }
+
+    /// Returns the bits added to the register address for write operations.
+    pub fn write_flag(&self) -> u8 {
+        self.write_flag
+    }
+
+    /// Sets the bits added to the register address for write operations.
+    ///
+    /// By default, `write_flag` is set to `0x00`.
+    pub fn set_write_flag(&mut self, write_flag: u8) {
+        self.write_flag = write_flag;
+    }
+
+    /// Returns the bits added to the register address for burst transfers.
+    pub fn auto_increment_flag(&self) -> u8 {
+        self.auto_increment_flag
+    }
+
+    /// Sets the bits added to the register address for burst transfers
+    /// of more than one register.
+    ///
+    /// By default, `auto_increment_flag` is set to `0x00`.
+    pub fn set_auto_increment_flag(&mut self, auto_increment_flag: u8) {
+        self.auto_increment_flag = auto_increment_flag;
+    }
+
+    /// Marks `register` as write-only, and sets its currently known `value`.
+    ///
+    /// Write-only registers can't be read back from the device. Instead,
+    /// [`read_u8`] and any read-modify-write operations use the last value
+    /// written to the register, starting with `value`, which should typically
+    /// be the register's reset value.
+    ///
+    /// Burst reads always access the device directly.
+    ///
+    /// [`read_u8`]: #method.read_u8
+    pub fn set_write_only(&mut self, register: u8, value: u8) {
+        self.cache.insert(register, value);
+    }
+
+    /// Returns the cached value of a write-only register, or `None` if
+    /// `register` isn't marked as write-only.
+    pub fn cached(&self, register: u8) -> Option<u8> {
+        self.cache.get(&register).copied()
+    }
+
+    /// Reads as many consecutive registers as can fit in `buffer`, starting at `register`.
+    pub fn read(&mut self, register: u8, buffer: &mut [u8]) -> result::Result<(), B::Error> {
+        let address = self.address(register, self.read_flag, buffer.len());
+
+        self.bus.read_registers(address, buffer)
+    }
+
+    /// Writes the contents of `buffer` to consecutive registers, starting at `register`.
+    pub fn write(&mut self, register: u8, buffer: &[u8]) -> result::Result<(), B::Error> {
+        let address = self.address(register, self.write_flag, buffer.len());
+
+        self.bus.write_registers(address, buffer)?;
+
+        // Keep track of any write-only registers that were included in the burst
+        for (offset, value) in buffer.iter().enumerate() {
+            if let Some(cached) = self.cache.get_mut(&register.wrapping_add(offset as u8)) {
+                *cached = *value;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an 8-bit value from `register`.
+    pub fn read_u8(&mut self, register: u8) -> result::Result<u8, B::Error> {
+        if let Some(value) = self.cached(register) {
+            return Ok(value);
+        }
+
+        let mut buffer = [0u8; 1];
+        self.read(register, &mut buffer)?;
+
+        Ok(buffer[0])
+    }
+
+    /// Reads a 16-bit value from 2 consecutive registers, starting at `register`.
+    pub fn read_u16(&mut self, register: u8) -> result::Result<u16, B::Error> {
+        let mut buffer = [0u8; 2];
+        self.read(register, &mut buffer)?;
+
+        Ok(match self.byte_order {
+            ByteOrder::BigEndian => u16::from_be_bytes(buffer),
+            ByteOrder::LittleEndian => u16::from_le_bytes(buffer),
+        })
+    }
+
+    /// Reads a 32-bit value from 4 consecutive registers, starting at `register`.
+    pub fn read_u32(&mut self, register: u8) -> result::Result<u32, B::Error> {
+        let mut buffer = [0u8; 4];
+        self.read(register, &mut buffer)?;
+
+        Ok(match self.byte_order {
+            ByteOrder::BigEndian => u32::from_be_bytes(buffer),
+            ByteOrder::LittleEndian => u32::from_le_bytes(buffer),
+        })
+    }
+
+    /// Writes an 8-bit value to `register`.
+    pub fn write_u8(&mut self, register: u8, value: u8) -> result::Result<(), B::Error> {
+        self.write(register, &[value])
+    }
+
+    /// Writes a 16-bit value to 2 consecutive registers, starting at `register`.
+    pub fn write_u16(&mut self, register: u8, value: u16) -> result::Result<(), B::Error> {
+        let buffer = match self.byte_order {
+            ByteOrder::BigEndian => value.to_be_bytes(),
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+        };
+
+        self.write(register, &buffer)
+    }
+
+    /// Writes a 32-bit value to 4 consecutive registers, starting at `register`.
+    pub fn write_u32(&mut self, register: u8, value: u32) -> result::Result<(), B::Error> {
+        let buffer = match self.byte_order {
+            ByteOrder::BigEndian => value.to_be_bytes(),
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+        };
+
+        self.write(register, &buffer)
+    }
+
+    /// Changes the bits selected by `mask` in `register` to the corresponding
+    /// bits in `value`, and leaves all other bits unchanged.
+    ///
+    /// The new value is only written if it differs from the current value.
+    pub fn update_bits(
+        &mut self,
+        register: u8,
+        mask: u8,
+        value: u8,
+    ) -> result::Result<(), B::Error> {
+        let current = self.read_u8(register)?;
+        let new = (current & !mask) | (value & mask);
+
+        if new != current {
+            self.write_u8(register, new)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the bit field selected by `mask` from `register`, shifted to the
+    /// least significant bits.
+    ///
+    /// For example, a `mask` of `0b0011_0000` returns a value between 0 and 3.
+    pub fn read_field(&mut self, register: u8, mask: u8) -> result::Result<u8, B::Error> {
+        if mask == 0 {
+            return Ok(0);
+        }
+
+        Ok((self.read_u8(register)? & mask) >> mask.trailing_zeros())
+    }
+
+    /// Writes `value` to the bit field selected by `mask` in `register`, and leaves
+    /// all other bits unchanged.
+    ///
+    /// `value` is shifted to the position of the least significant bit in `mask`.
+    /// For example, a `mask` of `0b0011_0000` accepts a value between 0 and 3.
+    pub fn write_field(
+        &mut self,
+        register: u8,
+        mask: u8,
+        value: u8,
+    ) -> result::Result<(), B::Error> {
+        if mask == 0 {
+            return Ok(());
+        }
+
+        self.update_bits(register, mask, value << mask.trailing_zeros())
+    }
+
+    // Adds the read/write flag, and the auto-increment flag for burst transfers
+    fn address(&self, register: u8, flag: u8, len: usize) -> u8 {
+        if len > 1 {
+            register | flag | self.auto_increment_flag
+        } else {
+            register | flag
+        }
+    }
+}
+
+impl<B: RegisterBus + fmt::Debug> fmt::Debug for RegisterDevice<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisterDevice")
+            .field("bus", &self.bus)
+            .field("byte_order", &self.byte_order)
+            .field("read_flag", &self.read_flag)
+            .field("write_flag", &self.write_flag)
+            .field("auto_increment_flag", &self.auto_increment_flag)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
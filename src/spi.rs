@@ -99,13 +99,77 @@
 //! Some of their functionality can be moved to different pins. Read
 //! `/boot/overlays/README` for more information.
 //!
+//! ### Bus IDs
+//!
+//! Bus IDs can differ between Raspberry Pi models, and may change depending on
+//! the order in which device tree overlays are loaded. Instead of selecting a bus
+//! by its ID, [`Spi::with_node`] selects the bus bound to a specific device tree
+//! node, and [`Spi::with_pins`] selects the bus that's routed to a specific SCLK pin.
+//!
+//! ## Shared buses
+//!
+//! [`Spi`] applies its settings to a single `spidev` device, and can't be
+//! shared between threads. [`SpiBus`] hands out [`SpiBusDevice`] handles for
+//! multiple slave devices on the same bus, each with its own clock speed, mode,
+//! bit order and Slave Select pin, which can be used from different threads.
+//!
 //! ## Buffer size limits
 //!
 //! By default, `spidev` can handle up to 4096 bytes in a single transfer. You
 //! can increase this limit to a maximum of 65536 bytes by appending
 //! `spidev.bufsiz=65536` to the single line of parameters in `/boot/firmware/cmdline.txt`.
 //! Remember to reboot the Raspberry Pi afterwards. The current value of bufsiz
-//! can be checked with `cat /sys/module/spidev/parameters/bufsiz`.
+//! can be checked with `cat /sys/module/spidev/parameters/bufsiz`, or by calling
+//! [`Spi::max_transfer_size`].
+//!
+//! When checking a multi-segment transfer against the limit, `spidev` rounds up
+//! the length of each segment to the DMA alignment of the SPI controller, which
+//! means a transfer with many short segments can exceed the limit even though
+//! its total length is smaller.
+//!
+//! Larger transfers are automatically split into multiple transfers that each fit
+//! within the limit. Slave Select is kept active in between, so the slave device
+//! receives the data as a single uninterrupted transfer, although there may be a
+//! short pause in the clock signal at each split.
+//!
+//! ## Prepared transfers
+//!
+//! [`PreparedTransfer`] stores a set of segments together with their own buffers,
+//! so the same transfer can be executed repeatedly with [`Spi::transfer_prepared`].
+//! Its memory can optionally be locked into RAM to avoid page faults during a
+//! transfer, which helps keep the latency of time-sensitive transfers consistent.
+//!
+//! A [`Segment`] already has the same layout as the structure the `spidev` driver
+//! expects, and [`Spi::transfer_segments`] doesn't allocate any memory for
+//! transfers that fit within [`Spi::max_transfer_size`], so a prepared transfer
+//! doesn't noticeably reduce the average time per transfer.
+//!
+//! ## Word sizes
+//!
+//! [`Spi::read`], [`Spi::write`] and [`Spi::transfer`] send and receive data
+//! as bytes. For slave devices that use larger words, such as 16-bit DACs or
+//! LCD controllers that expect 9-bit words, [`Spi::transfer_u16`] and
+//! [`Spi::transfer_u32`] (and their read and write counterparts) transfer
+//! `u16` and `u32` words in the layout expected by `spidev`. The number of bits
+//! per word set through [`Spi::set_bits_per_word`] is used if it fits the word
+//! type. Otherwise, the full 16 or 32 bits are transferred.
+//!
+//! Support for more than 8 bits per word depends on the SPI controller and
+//! its driver.
+//!
+//! ## Mode flags
+//!
+//! [`Spi::set_mode_flags`] configures the optional `spidev` mode flags
+//! `SPI_3WIRE` (bidirectional mode), `SPI_LOOP` (loopback mode), `SPI_NO_CS`
+//! (no Slave Select), `SPI_CS_HIGH` (active-high Slave Select), and
+//! `SPI_TX_DUAL`/`SPI_RX_DUAL` (dual SPI) or `SPI_TX_QUAD`/`SPI_RX_QUAD` (quad SPI).
+//! The number of data lines used for a specific [`Segment`] can be set with
+//! [`Segment::set_tx_nbits`] and [`Segment::set_rx_nbits`].
+//!
+//! Support for these flags depends on the SPI controller and its driver. The
+//! BCM283x SoC doesn't support dual or quad SPI, while some flags are available
+//! on the Raspberry Pi 5's RP1 or through certain device tree overlays. Flags
+//! that are rejected by the driver result in an error.
 //!
 //! ## Not supported
 //!
@@ -114,34 +178,79 @@
 //! first bit order), `SPI_3WIRE` (bidirectional mode), `SPI_LOOP` (loopback mode),
 //! `SPI_NO_CS` (no Slave Select), `SPI_READY` (slave ready signal),
 //! `SPI_TX_DUAL`/`SPI_RX_DUAL` (dual SPI), `SPI_TX_QUAD`/`SPI_RX_QUAD` (quad SPI),
-//! and any number of bits per word other than 8.
+//! and any number of bits per word other than 8. Some of these flags are
+//! available on other SPI controllers, as described under [Mode flags](#mode-flags).
 //!
-//! If your slave device requires `SPI_LSB_FIRST`, you can use the
-//! [`reverse_bits`] function instead to reverse the bit order in software.
+//! If your slave device requires `SPI_LSB_FIRST`, you can enable bit order
+//! emulation with [`Spi::set_bit_order_emulation`], which transparently reverses
+//! the bit order in software, or use the [`reverse_bits`] function to reverse
+//! the bit order of your buffers manually.
 //!
 //! `SPI_LOOP` mode can be achieved by connecting the MOSI and MISO pins
 //! together.
 //!
 //! `SPI_NO_CS` can be implemented by connecting the Slave Select pin on your
 //! slave device to any other available GPIO pin on the Pi, and manually
-//! changing it to high and low as needed.
+//! changing it to high and low as needed. [`GpioCsDevice`] automates this, and
+//! can be used to connect more slave devices than the bus supports natively.
+//!
+//! ## Software SPI
 //!
+//! In addition to the hardware SPI buses, [`SoftSpi`] offers a bit-banged
+//! software SPI master on any available GPIO pins. `SoftSpi` supports all 4 SPI
+//! modes, the `LsbFirst` bit order and any number of bits per word between 1 and
+//! 32, at the cost of a significantly lower and less consistent clock speed.
+//!
+//! [`Spi::with_node`]: struct.Spi.html#method.with_node
+//! [`Spi::with_pins`]: struct.Spi.html#method.with_pins
 //! [`Ss0`]: enum.SlaveSelect.html
 //! [`Ss1`]: enum.SlaveSelect.html
 //! [`Ss2`]: enum.SlaveSelect.html
 //! [`Mode1`]: enum.Mode.html
 //! [`Mode3`]: enum.Mode.html
 //! [`reverse_bits`]: fn.reverse_bits.html
+//! [`SoftSpi`]: struct.SoftSpi.html
+//! [`GpioCsDevice`]: struct.GpioCsDevice.html
+//! [`Spi`]: struct.Spi.html
+//! [`Spi::max_transfer_size`]: struct.Spi.html#method.max_transfer_size
+//! [`Spi::set_mode_flags`]: struct.Spi.html#method.set_mode_flags
+//! [`Spi::transfer_prepared`]: struct.Spi.html#method.transfer_prepared
+//! [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+//! [`PreparedTransfer`]: struct.PreparedTransfer.html
+//! [`Spi::set_bit_order_emulation`]: struct.Spi.html#method.set_bit_order_emulation
+//! [`Spi::read`]: struct.Spi.html#method.read
+//! [`Spi::write`]: struct.Spi.html#method.write
+//! [`Spi::transfer`]: struct.Spi.html#method.transfer
+//! [`Spi::transfer_u16`]: struct.Spi.html#method.transfer_u16
+//! [`Spi::transfer_u32`]: struct.Spi.html#method.transfer_u32
+//! [`Spi::set_bits_per_word`]: struct.Spi.html#method.set_bits_per_word
+//! [`Segment`]: struct.Segment.html
+//! [`Segment::set_tx_nbits`]: struct.Segment.html#method.set_tx_nbits
+//! [`Segment::set_rx_nbits`]: struct.Segment.html#method.set_rx_nbits
+//! [`SpiBus`]: struct.SpiBus.html
+//! [`SpiBusDevice`]: struct.SpiBusDevice.html
 
+use std::cell::Cell;
 use std::error;
 use std::fmt;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
+use std::mem;
 use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::result;
+use std::slice;
+use std::time::Duration;
+
+use crate::system::devicetree;
+use crate::trace::{Direction, Interface, TraceStart, Tracer};
 
+#[cfg(feature = "embedded-hal-async")]
+mod async_spi;
+mod bus;
+mod gpio_cs_device;
 #[cfg(any(
     feature = "embedded-hal-0",
     feature = "embedded-hal",
@@ -149,9 +258,17 @@ use std::result;
 ))]
 mod hal;
 mod ioctl;
+mod prepared_transfer;
 mod segment;
+mod soft_spi;
 
+#[cfg(feature = "embedded-hal-async")]
+pub use self::async_spi::AsyncSpi;
+pub use self::bus::{SpiBus, SpiBusDevice};
+pub use self::gpio_cs_device::GpioCsDevice;
+pub use self::prepared_transfer::PreparedTransfer;
 pub use self::segment::Segment;
+pub use self::soft_spi::SoftSpi;
 #[cfg(any(
     feature = "embedded-hal-0",
     feature = "embedded-hal",
@@ -159,6 +276,12 @@ pub use self::segment::Segment;
 ))]
 pub use hal::SimpleHalSpiDevice;
 
+// Default spidev bufsiz module parameter
+const BUFSIZ_DEFAULT: usize = 4096;
+// spidev rounds up the length of each segment to ARCH_DMA_MINALIGN when checking
+// it against bufsiz. This matches the largest value used on the Raspberry Pi.
+const DMA_MINALIGN: usize = 128;
+
 /// Errors that can occur when accessing the SPI peripheral.
 #[derive(Debug)]
 pub enum Error {
@@ -187,6 +310,17 @@ pub enum Error {
     ModeNotSupported(Mode),
     /// The specified Slave Select polarity is not supported.
     PolarityNotSupported(Polarity),
+    /// The specified word delay is not supported.
+    ///
+    /// Word delays are limited to 255 µs, and require Linux 5.3 or later.
+    WordDelayNotSupported(Duration),
+    /// The specified mode flags are not supported.
+    ///
+    /// Contains the flags passed to [`Spi::set_mode_flags`]. Support for each
+    /// flag depends on the SPI controller and its driver.
+    ///
+    /// [`Spi::set_mode_flags`]: struct.Spi.html#method.set_mode_flags
+    ModeFlagsNotSupported(ModeFlags),
 }
 
 impl fmt::Display for Error {
@@ -206,6 +340,12 @@ impl fmt::Display for Error {
             Error::PolarityNotSupported(polarity) => {
                 write!(f, "Polarity value not supported: {:?}", polarity)
             }
+            Error::WordDelayNotSupported(word_delay) => {
+                write!(f, "Word delay value not supported: {:?}", word_delay)
+            }
+            Error::ModeFlagsNotSupported(flags) => {
+                write!(f, "Mode flags not supported: {:?}", flags)
+            }
         }
     }
 }
@@ -228,8 +368,23 @@ pub type Result<T> = result::Result<T, Error>;
 #[inline(always)]
 pub fn reverse_bits(buffer: &mut [u8]) {
     for byte in buffer {
-        *byte = byte.reverse_bits();
+        *byte = REVERSE_BITS[*byte as usize];
+    }
+}
+
+// Lookup table containing the bit-reversed value of every byte
+static REVERSE_BITS: [u8; 256] = reverse_bits_table();
+
+const fn reverse_bits_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+
+    let mut i = 0;
+    while i < 256 {
+        table[i] = (i as u8).reverse_bits();
+        i += 1;
     }
+
+    table
 }
 
 /// SPI buses.
@@ -405,6 +560,82 @@ impl fmt::Display for BitOrder {
     }
 }
 
+/// Optional `spidev` mode flags.
+///
+/// `ModeFlags` is used with [`Spi::set_mode_flags`] to configure the flags
+/// that aren't covered by [`Mode`] and [`BitOrder`]. Support for each flag
+/// depends on the SPI controller and its driver. More information can be
+/// found [here].
+///
+/// By default, all flags are set to `false`.
+///
+/// [`Spi::set_mode_flags`]: struct.Spi.html#method.set_mode_flags
+/// [`Mode`]: enum.Mode.html
+/// [`BitOrder`]: enum.BitOrder.html
+/// [here]: index.html#mode-flags
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct ModeFlags {
+    /// Shares a single bidirectional data line for MOSI and MISO (`SPI_3WIRE`).
+    pub three_wire: bool,
+    /// Internally connects MOSI to MISO (`SPI_LOOP`).
+    pub loopback: bool,
+    /// Leaves the hardware Slave Select pin untouched (`SPI_NO_CS`).
+    pub no_cs: bool,
+    /// Sets Slave Select to active-high (`SPI_CS_HIGH`).
+    pub cs_high: bool,
+    /// Sends data on 2 outgoing lines (`SPI_TX_DUAL`).
+    pub tx_dual: bool,
+    /// Sends data on 4 outgoing lines (`SPI_TX_QUAD`).
+    pub tx_quad: bool,
+    /// Receives data on 2 incoming lines (`SPI_RX_DUAL`).
+    pub rx_dual: bool,
+    /// Receives data on 4 incoming lines (`SPI_RX_QUAD`).
+    pub rx_quad: bool,
+}
+
+impl ModeFlags {
+    // All mode32 bits covered by ModeFlags
+    const MASK: u32 =
+        (ioctl::MODE_3WIRE | ioctl::MODE_LOOP | ioctl::MODE_NO_CS | ioctl::MODE_CS_HIGH) as u32
+            | ioctl::MODE_TX_DUAL
+            | ioctl::MODE_TX_QUAD
+            | ioctl::MODE_RX_DUAL
+            | ioctl::MODE_RX_QUAD;
+
+    /// Constructs a new `ModeFlags` with all flags set to `false`.
+    pub fn new() -> ModeFlags {
+        ModeFlags::default()
+    }
+
+    fn from_bits(bits: u32) -> ModeFlags {
+        let flag = |mask: u32| (bits & mask) != 0;
+
+        ModeFlags {
+            three_wire: flag(u32::from(ioctl::MODE_3WIRE)),
+            loopback: flag(u32::from(ioctl::MODE_LOOP)),
+            no_cs: flag(u32::from(ioctl::MODE_NO_CS)),
+            cs_high: flag(u32::from(ioctl::MODE_CS_HIGH)),
+            tx_dual: flag(ioctl::MODE_TX_DUAL),
+            tx_quad: flag(ioctl::MODE_TX_QUAD),
+            rx_dual: flag(ioctl::MODE_RX_DUAL),
+            rx_quad: flag(ioctl::MODE_RX_QUAD),
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        let flag = |enabled: bool, mask: u32| if enabled { mask } else { 0 };
+
+        flag(self.three_wire, u32::from(ioctl::MODE_3WIRE))
+            | flag(self.loopback, u32::from(ioctl::MODE_LOOP))
+            | flag(self.no_cs, u32::from(ioctl::MODE_NO_CS))
+            | flag(self.cs_high, u32::from(ioctl::MODE_CS_HIGH))
+            | flag(self.tx_dual, ioctl::MODE_TX_DUAL)
+            | flag(self.tx_quad, ioctl::MODE_TX_QUAD)
+            | flag(self.rx_dual, ioctl::MODE_RX_DUAL)
+            | flag(self.rx_quad, ioctl::MODE_RX_QUAD)
+    }
+}
+
 /// Provides access to the Raspberry Pi's SPI peripherals.
 ///
 /// Before using `Spi`, make sure your Raspberry Pi has the necessary SPI buses
@@ -416,6 +647,13 @@ impl fmt::Display for BitOrder {
 /// [here]: index.html
 pub struct Spi {
     spidev: File,
+    max_transfer_size: usize,
+    word_delay_supported: bool,
+    bit_order_emulation: Cell<bool>,
+    lsb_first_emulated: Cell<bool>,
+    tracer: Option<Tracer>,
+    // Bus ID and Slave Select line included in trace records
+    trace_ids: (Option<u8>, Option<u16>),
     // Stores the last read value. Used for embedded_hal::spi::FullDuplex.
     #[cfg(any(feature = "embedded-hal-0", feature = "embedded-hal-nb"))]
     last_read: Option<u8>,
@@ -436,6 +674,22 @@ impl Spi {
     ///
     /// `mode` selects the clock polarity and phase.
     pub fn new(bus: Bus, slave_select: SlaveSelect, clock_speed: u32, mode: Mode) -> Result<Spi> {
+        Spi::with_path(
+            format!("/dev/spidev{}.{}", bus as u8, slave_select as u8),
+            clock_speed,
+            mode,
+        )
+    }
+
+    /// Constructs a new `Spi` using the `spidev` character device specified by `path`.
+    ///
+    /// `with_path` can be used with device paths that don't follow the
+    /// `/dev/spidevB.S` naming scheme, such as symlinks created by udev rules.
+    ///
+    /// `clock_speed` and `mode` are applied similarly to [`new`].
+    ///
+    /// [`new`]: #method.new
+    pub fn with_path<P: AsRef<Path>>(path: P, clock_speed: u32, mode: Mode) -> Result<Spi> {
         // The following options currently aren't supported by spidev in Raspbian Stretch on the Pi:
         //
         // LSB_FIRST - ioctl() returns EINVAL when set
@@ -445,11 +699,10 @@ impl Spi {
         // READY - ioctl() returns EINVAL when set
         // TX_DUAL/TX_QUAD/RX_DUAL/RX_QUAD - Not supported by BCM283x
         // bits per word - any value other than 0 or 8 returns EINVAL when set
+        //
+        // Newer kernels and the RP1 support some of these, which can be enabled through set_mode_flags().
 
-        let spidev = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(format!("/dev/spidev{}.{}", bus as u8, slave_select as u8))?;
+        let spidev = OpenOptions::new().read(true).write(true).open(path)?;
 
         // Reset all mode flags
         if let Err(e) = ioctl::set_mode32(spidev.as_raw_fd(), mode as u32) {
@@ -462,6 +715,12 @@ impl Spi {
 
         let spi = Spi {
             spidev,
+            max_transfer_size: read_bufsiz(),
+            word_delay_supported: word_delay_supported(),
+            bit_order_emulation: Cell::new(false),
+            lsb_first_emulated: Cell::new(false),
+            tracer: None,
+            trace_ids: (None, None),
             #[cfg(any(feature = "embedded-hal-0", feature = "embedded-hal-nb"))]
             last_read: None,
             not_sync: PhantomData,
@@ -474,8 +733,62 @@ impl Spi {
         Ok(spi)
     }
 
+    /// Constructs a new `Spi` using the bus that's bound to the specified device
+    /// tree `node`.
+    ///
+    /// `node` contains either the node name of the SPI controller including its
+    /// unit address, for instance `spi@7e204000`, or its full path, for instance
+    /// `soc/spi@7e204000`. Compared to [`new`], `with_node` isn't affected by bus
+    /// IDs that change between Raspberry Pi models, or depending on the order in
+    /// which device tree overlays are loaded.
+    ///
+    /// Returns an `io::ErrorKind::NotFound` error if none of the SPI buses match,
+    /// or if the `spidev` device for `slave_select` isn't available.
+    ///
+    /// [`new`]: #method.new
+    pub fn with_node(
+        node: &str,
+        slave_select: SlaveSelect,
+        clock_speed: u32,
+        mode: Mode,
+    ) -> Result<Spi> {
+        Spi::with_path(
+            find_device(slave_select, |path| devicetree::node_matches(path, node))?,
+            clock_speed,
+            mode,
+        )
+    }
+
+    /// Constructs a new `Spi` using the bus that's routed to the specified SCLK pin.
+    ///
+    /// `sclk` contains a BCM GPIO pin number. The pin is matched against the pin
+    /// configuration specified by each bus's device tree node.
+    ///
+    /// Returns an `io::ErrorKind::NotFound` error if none of the SPI buses match,
+    /// or if the `spidev` device for `slave_select` isn't available.
+    pub fn with_pins(
+        sclk: u8,
+        slave_select: SlaveSelect,
+        clock_speed: u32,
+        mode: Mode,
+    ) -> Result<Spi> {
+        Spi::with_path(
+            find_device(slave_select, |path| {
+                devicetree::node_pins(path)
+                    .map(|pins| pins.contains(&sclk))
+                    .unwrap_or(false)
+            })?,
+            clock_speed,
+            mode,
+        )
+    }
+
     /// Gets the bit order.
     pub fn bit_order(&self) -> Result<BitOrder> {
+        if self.lsb_first_emulated.get() {
+            return Ok(BitOrder::LsbFirst);
+        }
+
         let mut bit_order: u8 = 0;
         ioctl::lsb_first(self.spidev.as_raw_fd(), &mut bit_order)?;
 
@@ -488,7 +801,8 @@ impl Spi {
     /// Sets the order in which bits are shifted out and in.
     ///
     /// The Raspberry Pi currently only supports the [`MsbFirst`] bit order. If you
-    /// need the [`LsbFirst`] bit order, you can use the [`reverse_bits`] function
+    /// need the [`LsbFirst`] bit order, you can enable bit order emulation with
+    /// [`set_bit_order_emulation`], or use the [`reverse_bits`] function
     /// instead to reverse the bit order in software by converting your write
     /// buffer before sending it to the slave device, and your read buffer after
     /// reading any incoming data.
@@ -497,10 +811,22 @@ impl Spi {
     ///
     /// [`MsbFirst`]: enum.BitOrder.html
     /// [`LsbFirst`]: enum.BitOrder.html
+    /// [`set_bit_order_emulation`]: #method.set_bit_order_emulation
     /// [`reverse_bits`]: fn.reverse_bits.html
     pub fn set_bit_order(&self, bit_order: BitOrder) -> Result<()> {
         match ioctl::set_lsb_first(self.spidev.as_raw_fd(), bit_order as u8) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.lsb_first_emulated.set(false);
+                Ok(())
+            }
+            Err(ref e)
+                if e.kind() == io::ErrorKind::InvalidInput
+                    && bit_order == BitOrder::LsbFirst
+                    && self.bit_order_emulation.get() =>
+            {
+                self.lsb_first_emulated.set(true);
+                Ok(())
+            }
             Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
                 Err(Error::BitOrderNotSupported(bit_order))
             }
@@ -508,6 +834,44 @@ impl Spi {
         }
     }
 
+    /// Returns `true` if bit order emulation is enabled.
+    pub fn bit_order_emulation(&self) -> bool {
+        self.bit_order_emulation.get()
+    }
+
+    /// Enables or disables bit order emulation.
+    ///
+    /// When bit order emulation is enabled, and the SPI driver doesn't support
+    /// the [`LsbFirst`] bit order, [`set_bit_order`] switches to reversing the bits
+    /// of all outgoing and incoming bytes in software instead of returning an error.
+    /// This applies to [`read`], [`write`], [`transfer`], [`transfer_segments`]
+    /// and the `embedded-hal` trait implementations.
+    ///
+    /// Emulation only supports 8 bits per word. Transfers that use more bits
+    /// per word return [`Error::BitOrderNotSupported`] while the `LsbFirst`
+    /// bit order is emulated.
+    ///
+    /// Disabling bit order emulation while `LsbFirst` is emulated reverts
+    /// the bit order to [`MsbFirst`].
+    ///
+    /// By default, `bit_order_emulation` is set to `false`.
+    ///
+    /// [`LsbFirst`]: enum.BitOrder.html
+    /// [`MsbFirst`]: enum.BitOrder.html
+    /// [`set_bit_order`]: #method.set_bit_order
+    /// [`read`]: #method.read
+    /// [`write`]: #method.write
+    /// [`transfer`]: #method.transfer
+    /// [`transfer_segments`]: #method.transfer_segments
+    /// [`Error::BitOrderNotSupported`]: enum.Error.html#variant.BitOrderNotSupported
+    pub fn set_bit_order_emulation(&self, enabled: bool) {
+        self.bit_order_emulation.set(enabled);
+
+        if !enabled {
+            self.lsb_first_emulated.set(false);
+        }
+    }
+
     /// Gets the number of bits per word.
     pub fn bits_per_word(&self) -> Result<u8> {
         let mut bits_per_word: u8 = 0;
@@ -557,12 +921,7 @@ impl Spi {
         let mut mode: u8 = 0;
         ioctl::mode(self.spidev.as_raw_fd(), &mut mode)?;
 
-        Ok(match mode & 0x03 {
-            0x01 => Mode::Mode1,
-            0x02 => Mode::Mode2,
-            0x03 => Mode::Mode3,
-            _ => Mode::Mode0,
-        })
+        Ok(mode_from_bits(u32::from(mode)))
     }
 
     /// Sets the SPI mode.
@@ -570,13 +929,13 @@ impl Spi {
     /// The SPI mode indicates the serial clock polarity and phase. Some modes
     /// may not be available depending on the SPI bus that's used.
     pub fn set_mode(&self, mode: Mode) -> Result<()> {
-        let mut new_mode: u8 = 0;
-        ioctl::mode(self.spidev.as_raw_fd(), &mut new_mode)?;
+        let mut new_mode: u32 = 0;
+        ioctl::mode32(self.spidev.as_raw_fd(), &mut new_mode)?;
 
         // Make sure we only replace the CPOL/CPHA bits
-        new_mode = (new_mode & !0x03) | (mode as u8);
+        new_mode = (new_mode & !0x03) | (mode as u32);
 
-        match ioctl::set_mode(self.spidev.as_raw_fd(), new_mode) {
+        match ioctl::set_mode32(self.spidev.as_raw_fd(), new_mode) {
             Ok(_) => Ok(()),
             Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
                 Err(Error::ModeNotSupported(mode))
@@ -601,16 +960,16 @@ impl Spi {
     ///
     /// By default, the Slave Select polarity is set to `ActiveLow`.
     pub fn set_ss_polarity(&self, polarity: Polarity) -> Result<()> {
-        let mut new_mode: u8 = 0;
-        ioctl::mode(self.spidev.as_raw_fd(), &mut new_mode)?;
+        let mut new_mode: u32 = 0;
+        ioctl::mode32(self.spidev.as_raw_fd(), &mut new_mode)?;
 
         if polarity == Polarity::ActiveHigh {
-            new_mode |= ioctl::MODE_CS_HIGH;
+            new_mode |= u32::from(ioctl::MODE_CS_HIGH);
         } else {
-            new_mode &= !ioctl::MODE_CS_HIGH;
+            new_mode &= !u32::from(ioctl::MODE_CS_HIGH);
         }
 
-        match ioctl::set_mode(self.spidev.as_raw_fd(), new_mode) {
+        match ioctl::set_mode32(self.spidev.as_raw_fd(), new_mode) {
             Ok(_) => Ok(()),
             Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
                 Err(Error::PolarityNotSupported(polarity))
@@ -619,6 +978,90 @@ impl Spi {
         }
     }
 
+    // Enables or disables SPI_NO_CS, which leaves the hardware Slave Select pin untouched
+    pub(crate) fn set_no_cs(&self, no_cs: bool) -> Result<()> {
+        let mut new_mode: u32 = 0;
+        ioctl::mode32(self.spidev.as_raw_fd(), &mut new_mode)?;
+
+        if no_cs {
+            new_mode |= u32::from(ioctl::MODE_NO_CS);
+        } else {
+            new_mode &= !u32::from(ioctl::MODE_NO_CS);
+        }
+
+        ioctl::set_mode32(self.spidev.as_raw_fd(), new_mode)?;
+
+        Ok(())
+    }
+
+    /// Gets the optional mode flags.
+    pub fn mode_flags(&self) -> Result<ModeFlags> {
+        let mut mode: u32 = 0;
+        ioctl::mode32(self.spidev.as_raw_fd(), &mut mode)?;
+
+        Ok(ModeFlags::from_bits(mode))
+    }
+
+    /// Sets the optional mode flags.
+    ///
+    /// `set_mode_flags` replaces all flags covered by [`ModeFlags`], which
+    /// includes the Slave Select polarity. The SPI mode and bit order are left
+    /// unchanged. More information can be found [here].
+    ///
+    /// If the driver rejects `SPI_CS_HIGH`, `set_mode_flags` returns
+    /// [`Error::PolarityNotSupported`]. If any of the other flags are rejected,
+    /// [`Error::ModeFlagsNotSupported`] is returned. Some drivers silently ignore
+    /// unsupported dual or quad flags, which are reported as
+    /// [`Error::ModeFlagsNotSupported`] as well. On error, the previous flags
+    /// are restored.
+    ///
+    /// [`ModeFlags`]: struct.ModeFlags.html
+    /// [here]: index.html#mode-flags
+    /// [`Error::PolarityNotSupported`]: enum.Error.html#variant.PolarityNotSupported
+    /// [`Error::ModeFlagsNotSupported`]: enum.Error.html#variant.ModeFlagsNotSupported
+    pub fn set_mode_flags(&self, flags: ModeFlags) -> Result<()> {
+        let fd = self.spidev.as_raw_fd();
+
+        let mut mode: u32 = 0;
+        ioctl::mode32(fd, &mut mode)?;
+
+        let new_mode = (mode & !ModeFlags::MASK) | flags.bits();
+        let cs_high = u32::from(ioctl::MODE_CS_HIGH);
+
+        match ioctl::set_mode32(fd, new_mode) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
+                // Check whether the driver rejected the Slave Select polarity, or one of the other flags
+                if (new_mode & cs_high) != (mode & cs_high)
+                    && ioctl::set_mode32(fd, (new_mode & !cs_high) | (mode & cs_high)).is_ok()
+                {
+                    ioctl::set_mode32(fd, mode)?;
+
+                    return Err(Error::PolarityNotSupported(if flags.cs_high {
+                        Polarity::ActiveHigh
+                    } else {
+                        Polarity::ActiveLow
+                    }));
+                }
+
+                return Err(Error::ModeFlagsNotSupported(flags));
+            }
+            Err(e) => return Err(Error::Io(e)),
+        }
+
+        // Unsupported dual/quad flags are cleared by the driver instead of returning EINVAL
+        let mut applied_mode: u32 = 0;
+        ioctl::mode32(fd, &mut applied_mode)?;
+
+        if (applied_mode & ModeFlags::MASK) != flags.bits() {
+            ioctl::set_mode32(fd, mode)?;
+
+            return Err(Error::ModeFlagsNotSupported(flags));
+        }
+
+        Ok(())
+    }
+
     /// Receives incoming data from the slave device and writes it to `buffer`.
     ///
     /// The SPI protocol doesn't indicate how much incoming data is waiting,
@@ -633,7 +1076,24 @@ impl Spi {
     ///
     /// Returns how many bytes were read.
     pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
-        Ok(self.spidev.read(buffer)?)
+        if buffer.len() <= self.max_transfer_size && !self.lsb_first_emulated.get() {
+            let start = self.trace_start();
+            let result = self.spidev.read(buffer).map_err(Error::Io);
+            if let Some(start) = start {
+                let read_data = match result {
+                    Ok(len) => buffer[..len].to_vec(),
+                    Err(_) => Vec::new(),
+                };
+                self.trace(start, Direction::Read, Vec::new(), read_data, &result);
+            }
+
+            return result;
+        }
+
+        let segment = Segment::with_read(buffer);
+        self.transfer_segments(&[segment])?;
+
+        Ok(segment.len())
     }
 
     /// Sends the outgoing data contained in `buffer` to the slave device.
@@ -645,7 +1105,26 @@ impl Spi {
     ///
     /// Returns how many bytes were written.
     pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
-        Ok(self.spidev.write(buffer)?)
+        if buffer.len() <= self.max_transfer_size && !self.lsb_first_emulated.get() {
+            let start = self.trace_start();
+            let result = self.spidev.write(buffer).map_err(Error::Io);
+            if let Some(start) = start {
+                self.trace(
+                    start,
+                    Direction::Write,
+                    buffer.to_vec(),
+                    Vec::new(),
+                    &result,
+                );
+            }
+
+            return result;
+        }
+
+        let segment = Segment::with_write(buffer);
+        self.transfer_segments(&[segment])?;
+
+        Ok(segment.len())
     }
 
     /// Sends and receives data at the same time.
@@ -665,11 +1144,120 @@ impl Spi {
     pub fn transfer(&self, read_buffer: &mut [u8], write_buffer: &[u8]) -> Result<usize> {
         let segment = Segment::new(read_buffer, write_buffer);
 
-        ioctl::transfer(self.spidev.as_raw_fd(), &[segment])?;
+        self.transfer_segments(&[segment])?;
 
         Ok(segment.len())
     }
 
+    /// Receives incoming 16-bit words from the slave device and writes them to `buffer`.
+    ///
+    /// If the number of bits per word is set to a value between 9 and 16, each
+    /// word is transferred using that number of bits. Otherwise, 16 bits per
+    /// word are used for this read. More information can be found [here].
+    ///
+    /// Returns how many words were read.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn read_u16(&mut self, buffer: &mut [u16]) -> Result<usize> {
+        self.transfer_words(Some(buffer), None)
+    }
+
+    /// Sends the outgoing 16-bit words contained in `buffer` to the slave device.
+    ///
+    /// If the number of bits per word is set to a value between 9 and 16, each
+    /// word is transferred using that number of bits. Otherwise, 16 bits per
+    /// word are used for this write. More information can be found [here].
+    ///
+    /// Returns how many words were written.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn write_u16(&mut self, buffer: &[u16]) -> Result<usize> {
+        self.transfer_words(None, Some(buffer))
+    }
+
+    /// Sends and receives 16-bit words at the same time.
+    ///
+    /// `transfer_u16` will only transfer as many words as the shortest of the two
+    /// buffers contains.
+    ///
+    /// If the number of bits per word is set to a value between 9 and 16, each
+    /// word is transferred using that number of bits. Otherwise, 16 bits per
+    /// word are used for this transfer. More information can be found [here].
+    ///
+    /// Returns how many words were transferred.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn transfer_u16(&self, read_buffer: &mut [u16], write_buffer: &[u16]) -> Result<usize> {
+        self.transfer_words(Some(read_buffer), Some(write_buffer))
+    }
+
+    /// Receives incoming 32-bit words from the slave device and writes them to `buffer`.
+    ///
+    /// If the number of bits per word is set to a value between 17 and 32, each
+    /// word is transferred using that number of bits. Otherwise, 32 bits per
+    /// word are used for this read. More information can be found [here].
+    ///
+    /// Returns how many words were read.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn read_u32(&mut self, buffer: &mut [u32]) -> Result<usize> {
+        self.transfer_words(Some(buffer), None)
+    }
+
+    /// Sends the outgoing 32-bit words contained in `buffer` to the slave device.
+    ///
+    /// If the number of bits per word is set to a value between 17 and 32, each
+    /// word is transferred using that number of bits. Otherwise, 32 bits per
+    /// word are used for this write. More information can be found [here].
+    ///
+    /// Returns how many words were written.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn write_u32(&mut self, buffer: &[u32]) -> Result<usize> {
+        self.transfer_words(None, Some(buffer))
+    }
+
+    /// Sends and receives 32-bit words at the same time.
+    ///
+    /// `transfer_u32` will only transfer as many words as the shortest of the two
+    /// buffers contains.
+    ///
+    /// If the number of bits per word is set to a value between 17 and 32, each
+    /// word is transferred using that number of bits. Otherwise, 32 bits per
+    /// word are used for this transfer. More information can be found [here].
+    ///
+    /// Returns how many words were transferred.
+    ///
+    /// [here]: index.html#word-sizes
+    pub fn transfer_u32(&self, read_buffer: &mut [u32], write_buffer: &[u32]) -> Result<usize> {
+        self.transfer_words(Some(read_buffer), Some(write_buffer))
+    }
+
+    // Transfers words as a single segment, using the spidev in-memory word layout
+    fn transfer_words<T: Word>(
+        &self,
+        read_buffer: Option<&mut [T]>,
+        write_buffer: Option<&[T]>,
+    ) -> Result<usize> {
+        let bits_per_word = match self.bits_per_word()? {
+            bits_per_word if (T::BITS_MIN..=T::BITS_MAX).contains(&bits_per_word) => bits_per_word,
+            _ => T::BITS_MAX,
+        };
+
+        let segment = Segment::with_settings(
+            read_buffer.map(words_as_bytes_mut),
+            write_buffer.map(words_as_bytes),
+            0,
+            0,
+            bits_per_word,
+            false,
+        );
+
+        self.transfer_segments(&[segment])?;
+
+        Ok(segment.len() / mem::size_of::<T>())
+    }
+
     /// Transfers multiple half-duplex or full-duplex segments.
     ///
     /// `transfer_segments` transfers multiple segments in a single call. Each
@@ -680,13 +1268,337 @@ impl Spi {
     /// By default, Slave Select stays active until all segments have been
     /// transferred. You can change this behavior using [`Segment::set_ss_change`].
     ///
+    /// If the combined length of all segments exceeds [`max_transfer_size`], the
+    /// segments are split into multiple transfers, while Slave Select is kept active.
+    ///
     /// [`Segment`]: struct.Segment.html
     /// [`Segment::set_ss_change`]: struct.Segment.html#method.set_ss_change
+    /// [`max_transfer_size`]: #method.max_transfer_size
     pub fn transfer_segments(&self, segments: &[Segment<'_, '_>]) -> Result<()> {
-        ioctl::transfer(self.spidev.as_raw_fd(), segments)?;
+        let start = self.trace_start();
+        let result = if self.lsb_first_emulated.get() {
+            self.transfer_segments_reversed(segments)
+        } else {
+            self.transfer_segments_raw(segments)
+        };
+
+        if let Some(start) = start {
+            self.trace_segments(start, segments, &result);
+        }
+
+        result
+    }
+
+    /// Transfers a [`PreparedTransfer`].
+    ///
+    /// If the transfer fits within [`max_transfer_size`], and the `LsbFirst` bit
+    /// order isn't emulated, the prepared segments are passed straight to the
+    /// `spidev` driver. Otherwise, `transfer_prepared` behaves the same as
+    /// [`transfer_segments`].
+    ///
+    /// [`PreparedTransfer`]: struct.PreparedTransfer.html
+    /// [`max_transfer_size`]: #method.max_transfer_size
+    /// [`transfer_segments`]: #method.transfer_segments
+    pub fn transfer_prepared(&self, prepared: &mut PreparedTransfer) -> Result<()> {
+        if !self.fits_single_transfer(prepared.segments())
+            || self.lsb_first_emulated.get()
+            || (prepared.has_word_delay() && !self.word_delay_supported)
+        {
+            return self.transfer_segments(prepared.segments());
+        }
+
+        let start = self.trace_start();
+        let result = ioctl::transfer(self.spidev.as_raw_fd(), prepared.segments())
+            .map(|_| ())
+            .map_err(Error::Io);
+
+        if let Some(start) = start {
+            self.trace_segments(start, prepared.segments(), &result);
+        }
+
+        result
+    }
+
+    // Returns the start time of an operation, if tracing is enabled
+    fn trace_start(&self) -> Option<TraceStart> {
+        self.tracer.as_ref().map(|_| TraceStart::now())
+    }
+
+    // Records a completed operation, if tracing is enabled
+    fn trace<T>(
+        &self,
+        start: TraceStart,
+        direction: Direction,
+        write_data: Vec<u8>,
+        read_data: Vec<u8>,
+        result: &Result<T>,
+    ) {
+        if let Some(tracer) = &self.tracer {
+            tracer.record(start.finish(
+                Interface::Spi,
+                self.trace_ids.0,
+                self.trace_ids.1,
+                direction,
+                write_data,
+                read_data,
+                result.as_ref().err().map(|e| e.to_string()),
+            ));
+        }
+    }
+
+    // Records each segment of a completed transfer separately
+    fn trace_segments(&self, start: TraceStart, segments: &[Segment<'_, '_>], result: &Result<()>) {
+        for segment in segments {
+            // Safe because the read buffer is only accessed after the transfer
+            // completes, and the caller can't access it until we return
+            let read_data = match (result, unsafe { segment.read_buffer() }) {
+                (Ok(()), Some(buffer)) => buffer.to_vec(),
+                _ => Vec::new(),
+            };
+
+            let direction = match (segment.has_read_buffer(), segment.write_buffer()) {
+                (true, Some(_)) => Direction::Transfer,
+                (true, None) => Direction::Read,
+                (false, _) => Direction::Write,
+            };
+
+            self.trace(
+                start,
+                direction,
+                segment.write_buffer().map_or_else(Vec::new, <[u8]>::to_vec),
+                read_data,
+                result,
+            );
+        }
+    }
+
+    // Emulates the LsbFirst bit order by reversing the bits of all outgoing and incoming bytes
+    fn transfer_segments_reversed(&self, segments: &[Segment<'_, '_>]) -> Result<()> {
+        // Segments with bits_per_word set to 0 use the default value
+        let default_bits_per_word = if segments.iter().any(|segment| segment.bits_per_word() == 0) {
+            self.bits_per_word()?
+        } else {
+            8
+        };
+
+        if segments.iter().any(|segment| {
+            segment.bits_per_word() > 8
+                || (segment.bits_per_word() == 0 && default_bits_per_word > 8)
+        }) {
+            return Err(Error::BitOrderNotSupported(BitOrder::LsbFirst));
+        }
+
+        let write_buffers: Vec<Vec<u8>> = segments
+            .iter()
+            .map(|segment| {
+                segment.write_buffer().map_or_else(Vec::new, |buffer| {
+                    buffer
+                        .iter()
+                        .map(|&byte| REVERSE_BITS[byte as usize])
+                        .collect()
+                })
+            })
+            .collect();
+
+        // Incoming data is received in copies, since the same read buffer could be
+        // referenced by multiple segments, and would be reversed more than once
+        let mut read_buffers: Vec<Vec<u8>> = segments
+            .iter()
+            .map(|segment| {
+                if segment.has_read_buffer() {
+                    vec![0u8; segment.len()]
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+
+        let reversed_segments: Vec<Segment<'_, '_>> = segments
+            .iter()
+            .zip(read_buffers.iter_mut())
+            .zip(&write_buffers)
+            .map(|((segment, read_buffer), write_buffer)| {
+                segment.with_buffers(
+                    if segment.has_read_buffer() {
+                        Some(read_buffer)
+                    } else {
+                        None
+                    },
+                    segment.write_buffer().map(|_| &write_buffer[..]),
+                )
+            })
+            .collect();
+
+        self.transfer_segments_raw(&reversed_segments)?;
+
+        for (segment, reversed) in segments.iter().zip(&read_buffers) {
+            // Safe because the read buffers are exclusively borrowed by the segments
+            if let Some(buffer) = unsafe { segment.read_buffer() } {
+                for (byte, &reversed) in buffer.iter_mut().zip(reversed) {
+                    *byte = REVERSE_BITS[reversed as usize];
+                }
+            }
+        }
 
         Ok(())
     }
+
+    fn transfer_segments_raw(&self, segments: &[Segment<'_, '_>]) -> Result<()> {
+        // Older kernels treat word_delay_usecs as padding, and silently ignore it
+        if !self.word_delay_supported {
+            if let Some(segment) = segments
+                .iter()
+                .find(|segment| segment.word_delay() > Duration::default())
+            {
+                return Err(Error::WordDelayNotSupported(segment.word_delay()));
+            }
+        }
+
+        if self.fits_single_transfer(segments) {
+            ioctl::transfer(self.spidev.as_raw_fd(), segments)?;
+
+            return Ok(());
+        }
+
+        let mut batch: Vec<Segment<'_, '_>> = Vec::new();
+        // Padded number of bytes received and sent in the current batch
+        let mut rx_len = 0;
+        let mut tx_len = 0;
+
+        for segment in segments {
+            let has_rx = segment.has_read_buffer();
+            let has_tx = segment.write_buffer().is_some();
+            let mut offset = 0;
+
+            loop {
+                let remaining = segment.len() - offset;
+                let used = if has_rx { rx_len } else { 0 }.max(if has_tx { tx_len } else { 0 });
+                let capacity = self.max_transfer_size.saturating_sub(used);
+
+                // Split at a DMA_MINALIGN boundary, so the chunks don't need any padding,
+                // and multi-byte words stay intact
+                let len = if (!has_rx && !has_tx) || dma_padded(remaining) <= capacity {
+                    remaining
+                } else if capacity >= DMA_MINALIGN {
+                    capacity - (capacity % DMA_MINALIGN)
+                } else if batch.is_empty() {
+                    (capacity & !0x03).max(capacity.min(0x03))
+                } else {
+                    0
+                };
+
+                if len == 0 && remaining > 0 {
+                    self.transfer_batch(&mut batch)?;
+                    rx_len = 0;
+                    tx_len = 0;
+                    continue;
+                }
+
+                let mut chunk = segment.chunk(offset, len);
+                offset += len;
+
+                // Segment-specific delays and Slave Select changes only apply to the final chunk
+                if offset < segment.len() {
+                    chunk.set_delay(0);
+                    chunk.set_ss_change(false);
+                }
+
+                batch.push(chunk);
+                if has_rx {
+                    rx_len += dma_padded(len);
+                }
+                if has_tx {
+                    tx_len += dma_padded(len);
+                }
+
+                if offset == segment.len() {
+                    break;
+                }
+
+                self.transfer_batch(&mut batch)?;
+                rx_len = 0;
+                tx_len = 0;
+            }
+        }
+
+        ioctl::transfer(self.spidev.as_raw_fd(), &batch)?;
+
+        Ok(())
+    }
+
+    // Returns true if spidev accepts segments in a single transfer. Incoming and outgoing
+    // data are checked against bufsiz separately, with each segment padded to DMA_MINALIGN.
+    fn fits_single_transfer(&self, segments: &[Segment<'_, '_>]) -> bool {
+        let mut rx_len = 0;
+        let mut tx_len = 0;
+
+        for segment in segments {
+            if segment.has_read_buffer() {
+                rx_len += dma_padded(segment.len());
+            }
+
+            if segment.write_buffer().is_some() {
+                tx_len += dma_padded(segment.len());
+            }
+        }
+
+        rx_len <= self.max_transfer_size && tx_len <= self.max_transfer_size
+    }
+
+    // Transfers an incomplete part of a split transfer, and clears batch
+    fn transfer_batch(&self, batch: &mut Vec<Segment<'_, '_>>) -> Result<()> {
+        // cs_change on the last segment keeps Slave Select active after the transfer,
+        // unless it was set to toggle Slave Select in between two segments
+        if let Some(last) = batch.last_mut() {
+            last.set_ss_change(!last.ss_change());
+        }
+
+        ioctl::transfer(self.spidev.as_raw_fd(), batch)?;
+        batch.clear();
+
+        Ok(())
+    }
+
+    /// Returns the attached [`Tracer`], if any.
+    ///
+    /// [`Tracer`]: ../trace/struct.Tracer.html
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Attaches a [`Tracer`] that records every read, write and transfer.
+    ///
+    /// Each [`Segment`] is recorded separately. The bus ID and Slave Select
+    /// line included in the records are based on the name of the underlying
+    /// `spidev` device, and are left empty if the name doesn't follow the
+    /// `/dev/spidevB.S` naming scheme.
+    ///
+    /// Set `tracer` to `None` to disable tracing. More information can be
+    /// found in the [`trace`] module documentation.
+    ///
+    /// By default, tracing is disabled.
+    ///
+    /// [`Tracer`]: ../trace/struct.Tracer.html
+    /// [`Segment`]: struct.Segment.html
+    /// [`trace`]: ../trace/index.html
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        if tracer.is_some() {
+            self.trace_ids = spidev_ids(&self.spidev);
+        }
+
+        self.tracer = tracer;
+    }
+
+    /// Returns the maximum number of bytes `spidev` can handle in a single transfer.
+    ///
+    /// The limit is based on the `bufsiz` parameter of the `spidev` kernel module,
+    /// which is read when `Spi` is constructed. Larger transfers are automatically
+    /// split into multiple transfers. More information can be found [here].
+    ///
+    /// [here]: index.html#buffer-size-limits
+    pub fn max_transfer_size(&self) -> usize {
+        self.max_transfer_size
+    }
 }
 
 // Send is safe for Spi, but we're marked !Send because of the dummy pointer that's
@@ -698,3 +1610,121 @@ impl fmt::Debug for Spi {
         f.debug_struct("Spi").field("spidev", &self.spidev).finish()
     }
 }
+
+// Word types for transfers with more than 8 bits per word. spidev expects words with
+// 9-16 bits to be stored as u16, and words with 17-32 bits as u32, in host byte order.
+trait Word: Copy {
+    const BITS_MIN: u8;
+    const BITS_MAX: u8;
+}
+
+impl Word for u16 {
+    const BITS_MIN: u8 = 9;
+    const BITS_MAX: u8 = 16;
+}
+
+impl Word for u32 {
+    const BITS_MIN: u8 = 17;
+    const BITS_MAX: u8 = 32;
+}
+
+fn words_as_bytes<T: Word>(words: &[T]) -> &[u8] {
+    // Safe because u16 and u32 don't contain any padding
+    unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, mem::size_of_val(words)) }
+}
+
+fn words_as_bytes_mut<T: Word>(words: &mut [T]) -> &mut [u8] {
+    // Safe because any bit pattern is a valid u16 or u32
+    unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, mem::size_of_val(words)) }
+}
+
+// Converts the CPOL/CPHA bits to Mode
+fn mode_from_bits(mode: u32) -> Mode {
+    match mode & 0x03 {
+        0x01 => Mode::Mode1,
+        0x02 => Mode::Mode2,
+        0x03 => Mode::Mode3,
+        _ => Mode::Mode0,
+    }
+}
+
+// Checks whether the running kernel supports word_delay_usecs, which was added in Linux 5.3
+fn word_delay_supported() -> bool {
+    let release = match fs::read_to_string("/proc/sys/kernel/osrelease") {
+        Ok(release) => release,
+        Err(_) => return true,
+    };
+
+    let mut version = release
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+
+    (version.next().unwrap_or(0), version.next().unwrap_or(0)) >= (5, 3)
+}
+
+// Parses the bus ID and Slave Select line from the spidevB.S device name
+fn spidev_ids(spidev: &File) -> (Option<u8>, Option<u16>) {
+    let path = match fs::read_link(format!("/proc/self/fd/{}", spidev.as_raw_fd())) {
+        Ok(path) => path,
+        Err(_) => return (None, None),
+    };
+
+    let ids = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("spidev"))
+        .and_then(|ids| ids.split_once('.'));
+
+    match ids {
+        Some((bus, slave_select)) => (bus.parse().ok(), slave_select.parse().ok()),
+        None => (None, None),
+    }
+}
+
+// Rounds up len to a multiple of DMA_MINALIGN
+fn dma_padded(len: usize) -> usize {
+    (len + DMA_MINALIGN - 1) / DMA_MINALIGN * DMA_MINALIGN
+}
+
+// Reads the spidev bufsiz module parameter, which limits the size of a single transfer
+fn read_bufsiz() -> usize {
+    fs::read_to_string("/sys/module/spidev/parameters/bufsiz")
+        .ok()
+        .and_then(|bufsiz| bufsiz.trim().parse::<usize>().ok())
+        .filter(|&bufsiz| bufsiz > 0)
+        .unwrap_or(BUFSIZ_DEFAULT)
+}
+
+// Returns the spidev path for slave_select on the first SPI bus in
+// /sys/bus/spi/devices whose controller matches predicate
+fn find_device<F>(slave_select: SlaveSelect, predicate: F) -> Result<PathBuf>
+where
+    F: Fn(&Path) -> bool,
+{
+    for entry in fs::read_dir("/sys/bus/spi/devices")? {
+        let entry = entry?;
+
+        // Devices are listed as spiB.C, where B is the bus ID and C the chip select
+        let name = entry.file_name();
+        let (bus, chip_select) = match name
+            .to_str()
+            .and_then(|name| name.strip_prefix("spi"))
+            .and_then(|name| name.split_once('.'))
+        {
+            Some(ids) => ids,
+            None => continue,
+        };
+
+        if chip_select.parse::<u8>().ok() != Some(slave_select as u8) {
+            continue;
+        }
+
+        // The parent directory belongs to the SPI controller
+        let device = fs::canonicalize(entry.path())?;
+        if device.parent().map_or(false, &predicate) {
+            return Ok(PathBuf::from(format!("/dev/spidev{}.{}", bus, chip_select)));
+        }
+    }
+
+    Err(Error::Io(io::Error::from(io::ErrorKind::NotFound)))
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use std::io;
+
+use embedded_hal::spi::Operation;
+
+use crate::worker::Worker;
+
+use super::{Error, Result, Segment, Spi};
+
+// Owned copy of an embedded-hal operation, which can be moved to the worker thread
+enum OwnedOperation {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+    Transfer(Vec<u8>, Vec<u8>),
+    DelayNs(u32),
+}
+
+/// Provides asynchronous access to an SPI bus.
+///
+/// `AsyncSpi` moves an [`Spi`] instance to a dedicated worker thread, where
+/// transactions are executed using blocking calls. Each transaction returns a
+/// future that resolves once the worker has completed it, which makes `AsyncSpi`
+/// usable with any async runtime.
+///
+/// All data is copied to and from the worker thread, so dropping a future before
+/// it resolves doesn't affect any borrowed buffers. The transaction itself is
+/// still completed in the background.
+///
+/// `AsyncSpi` implements the `embedded-hal-async` `SpiDevice` trait, which can be
+/// enabled by specifying the optional `embedded-hal-async` feature in the dependency
+/// declaration for the `rppal` crate. Slave Select is handled by the underlying
+/// `Spi`, and kept active for the duration of each transaction.
+///
+/// [`Spi`]: struct.Spi.html
+pub struct AsyncSpi {
+    worker: Worker<Spi>,
+}
+
+impl AsyncSpi {
+    /// Constructs a new `AsyncSpi`, and starts its worker thread.
+    pub fn new(spi: Spi) -> Result<AsyncSpi> {
+        Ok(AsyncSpi {
+            worker: Worker::new("rppal-spi", spi)?,
+        })
+    }
+
+    async fn transaction(&self, operations: &mut [Operation<'_, u8>]) -> Result<()> {
+        let mut owned: Vec<OwnedOperation> = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buffer) => OwnedOperation::Read(vec![0; buffer.len()]),
+                Operation::Write(buffer) => OwnedOperation::Write(buffer.to_vec()),
+                Operation::Transfer(read_buffer, write_buffer) => {
+                    OwnedOperation::Transfer(vec![0; read_buffer.len()], write_buffer.to_vec())
+                }
+                Operation::TransferInPlace(buffer) => {
+                    OwnedOperation::Transfer(vec![0; buffer.len()], buffer.to_vec())
+                }
+                Operation::DelayNs(delay_ns) => OwnedOperation::DelayNs(*delay_ns),
+            })
+            .collect();
+
+        let (result, owned) = self
+            .worker
+            .run(move |spi| {
+                let result = transfer_operations(spi, &mut owned);
+                (result, owned)
+            })
+            .await
+            .ok_or_else(worker_stopped)?;
+
+        result?;
+
+        for (op, owned) in operations.iter_mut().zip(owned) {
+            match (op, owned) {
+                (Operation::Read(buffer), OwnedOperation::Read(data))
+                | (Operation::Transfer(buffer, _), OwnedOperation::Transfer(data, _))
+                | (Operation::TransferInPlace(buffer), OwnedOperation::Transfer(data, _)) => {
+                    let len = buffer.len().min(data.len());
+                    buffer[..len].copy_from_slice(&data[..len]);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AsyncSpi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncSpi").finish_non_exhaustive()
+    }
+}
+
+impl embedded_hal::spi::ErrorType for AsyncSpi {
+    type Error = Error;
+}
+
+impl embedded_hal_async::spi::SpiDevice<u8> for AsyncSpi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<()> {
+        AsyncSpi::transaction(self, operations).await
+    }
+}
+
+// Transfers all operations as segments of a single transfer, so Slave Select stays active
+fn transfer_operations(spi: &Spi, operations: &mut [OwnedOperation]) -> Result<()> {
+    let segments: Vec<Segment<'_, '_>> = operations
+        .iter_mut()
+        .map(|op| match op {
+            OwnedOperation::Read(buffer) => Segment::with_read(buffer),
+            OwnedOperation::Write(buffer) => Segment::with_write(buffer),
+            OwnedOperation::Transfer(read_buffer, write_buffer) => {
+                Segment::new(read_buffer, write_buffer)
+            }
+            // A segment without any buffers only adds a delay
+            OwnedOperation::DelayNs(delay_ns) => {
+                let delay_us = (u64::from(*delay_ns) + 999) / 1000;
+                Segment::with_settings(
+                    None,
+                    None,
+                    0,
+                    delay_us.min(u64::from(u16::MAX)) as u16,
+                    0,
+                    false,
+                )
+            }
+        })
+        .collect();
+
+    spi.transfer_segments(&segments)
+}
+
+fn worker_stopped() -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "SPI worker thread stopped",
+    ))
+}
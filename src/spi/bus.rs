@@ -0,0 +1,378 @@
+use std::fmt;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::{Arc, Mutex};
+
+use crate::gpio::{Level, OutputPin};
+
+use super::{ioctl, BitOrder, Bus, Error, Mode, Polarity, Result, Segment, SlaveSelect, Spi};
+
+// An opened spidev device, and the ID of the device whose settings are currently applied
+struct Channel {
+    slave_select: SlaveSelect,
+    spi: Spi,
+    active: Option<u64>,
+}
+
+struct BusState {
+    channels: Vec<Channel>,
+    next_id: u64,
+}
+
+impl BusState {
+    fn channel(&mut self, slave_select: SlaveSelect) -> Option<&mut Channel> {
+        self.channels
+            .iter_mut()
+            .find(|channel| channel.slave_select == slave_select)
+    }
+}
+
+/// Provides shared access to an SPI bus.
+///
+/// `SpiBus` hands out [`SpiBusDevice`] handles for each slave device connected
+/// to the bus, either through one of the bus's hardware Slave Select pins, or
+/// through any available GPIO pin. The handles can be used by different drivers,
+/// either on the same thread or on separate threads. Each access through an
+/// `SpiBusDevice` locks the bus for the duration of the operation.
+///
+/// Cloning an `SpiBus` creates a new reference to the same bus.
+///
+/// [`SpiBusDevice`]: struct.SpiBusDevice.html
+#[derive(Clone)]
+pub struct SpiBus {
+    bus: Bus,
+    state: Arc<Mutex<BusState>>,
+}
+
+impl SpiBus {
+    /// Constructs a new `SpiBus`.
+    ///
+    /// The `spidev` devices for the selected bus are opened when the first
+    /// [`SpiBusDevice`] that needs them is created.
+    ///
+    /// [`SpiBusDevice`]: struct.SpiBusDevice.html
+    pub fn new(bus: Bus) -> SpiBus {
+        SpiBus {
+            bus,
+            state: Arc::new(Mutex::new(BusState {
+                channels: Vec::new(),
+                next_id: 0,
+            })),
+        }
+    }
+
+    /// Returns the SPI bus.
+    pub fn bus(&self) -> Bus {
+        self.bus
+    }
+
+    /// Returns a new [`SpiBusDevice`] for the slave device connected to the
+    /// hardware Slave Select pin `slave_select`.
+    ///
+    /// `clock_speed` and `mode` are applied whenever the bus switches to this device.
+    ///
+    /// [`SpiBusDevice`]: struct.SpiBusDevice.html
+    pub fn device(
+        &self,
+        slave_select: SlaveSelect,
+        clock_speed: u32,
+        mode: Mode,
+    ) -> Result<SpiBusDevice> {
+        self.new_device(slave_select, None, clock_speed, mode)
+    }
+
+    /// Returns a new [`SpiBusDevice`] for the slave device connected to the
+    /// GPIO pin `ss`.
+    ///
+    /// Transfers for GPIO Slave Select devices are sent through the `spidev`
+    /// device for [`SlaveSelect::Ss0`], configured with `SPI_NO_CS`. The hardware
+    /// Slave Select pin for `Ss0` is left untouched during those transfers.
+    ///
+    /// `clock_speed` and `mode` are applied whenever the bus switches to this device.
+    ///
+    /// [`SpiBusDevice`]: struct.SpiBusDevice.html
+    /// [`SlaveSelect::Ss0`]: enum.SlaveSelect.html
+    pub fn gpio_device(&self, ss: OutputPin, clock_speed: u32, mode: Mode) -> Result<SpiBusDevice> {
+        let mut device = self.new_device(SlaveSelect::Ss0, Some(ss), clock_speed, mode)?;
+        device.deselect();
+
+        Ok(device)
+    }
+
+    fn new_device(
+        &self,
+        slave_select: SlaveSelect,
+        ss: Option<OutputPin>,
+        clock_speed: u32,
+        mode: Mode,
+    ) -> Result<SpiBusDevice> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.channel(slave_select).is_none() {
+            let spi = Spi::new(self.bus, slave_select, clock_speed, mode)?;
+            // Devices that need the LsbFirst bit order fall back to software emulation
+            spi.set_bit_order_emulation(true);
+            state.channels.push(Channel {
+                slave_select,
+                spi,
+                active: None,
+            });
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+
+        Ok(SpiBusDevice {
+            bus: self.clone(),
+            id,
+            slave_select,
+            ss,
+            clock_speed,
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            ss_polarity: Polarity::ActiveLow,
+        })
+    }
+}
+
+impl fmt::Debug for SpiBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiBus")
+            .field("bus", &self.bus)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Provides access to a single slave device on a shared [`SpiBus`].
+///
+/// Each `SpiBusDevice` stores its own clock speed, mode, bit order and Slave
+/// Select polarity. Before every access, the bus is locked, and the device's
+/// settings are applied if a different device accessed the bus last. If the
+/// previous access was made by the same device, no additional ioctl calls
+/// are needed.
+///
+/// Settings are validated when they're applied, which means an unsupported
+/// value is reported by the next read, write or transfer.
+///
+/// `SpiBusDevice` implements the `embedded-hal` `SpiDevice` trait, which makes it
+/// possible for several drivers to share the same bus.
+///
+/// [`SpiBus`]: struct.SpiBus.html
+pub struct SpiBusDevice {
+    bus: SpiBus,
+    id: u64,
+    slave_select: SlaveSelect,
+    ss: Option<OutputPin>,
+    clock_speed: u32,
+    mode: Mode,
+    bit_order: BitOrder,
+    ss_polarity: Polarity,
+}
+
+impl SpiBusDevice {
+    /// Returns the hardware Slave Select pin used by this device.
+    ///
+    /// Returns `None` if the device uses a GPIO pin as Slave Select.
+    pub fn slave_select(&self) -> Option<SlaveSelect> {
+        if self.ss.is_none() {
+            Some(self.slave_select)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the clock frequency in hertz (Hz).
+    pub fn clock_speed(&self) -> u32 {
+        self.clock_speed
+    }
+
+    /// Sets the clock frequency in hertz (Hz).
+    pub fn set_clock_speed(&mut self, clock_speed: u32) {
+        self.clock_speed = clock_speed;
+        self.invalidate();
+    }
+
+    /// Returns the SPI mode.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets the SPI mode.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.invalidate();
+    }
+
+    /// Returns the bit order.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Sets the order in which bits are shifted out and in.
+    ///
+    /// If the SPI driver doesn't support the `LsbFirst` bit order, the bits are
+    /// reversed in software instead. More information can be found in the
+    /// documentation for [`Spi::set_bit_order_emulation`].
+    ///
+    /// By default, `bit_order` is set to `MsbFirst`.
+    ///
+    /// [`Spi::set_bit_order_emulation`]: struct.Spi.html#method.set_bit_order_emulation
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+        self.invalidate();
+    }
+
+    /// Returns the Slave Select polarity.
+    pub fn ss_polarity(&self) -> Polarity {
+        self.ss_polarity
+    }
+
+    /// Sets the Slave Select polarity.
+    ///
+    /// By default, the Slave Select polarity is set to `ActiveLow`.
+    pub fn set_ss_polarity(&mut self, polarity: Polarity) {
+        self.ss_polarity = polarity;
+        self.invalidate();
+
+        if self.ss.is_some() {
+            self.deselect();
+        }
+    }
+
+    /// Locks the bus, applies this device's settings, and calls `f` with
+    /// exclusive access to the underlying [`Spi`].
+    ///
+    /// For GPIO Slave Select devices, Slave Select is set to active before
+    /// calling `f`, and inactive afterwards.
+    ///
+    /// [`Spi`]: struct.Spi.html
+    pub fn access<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Spi) -> Result<T>,
+    {
+        let state = self.bus.state.clone();
+        let mut state = state.lock().unwrap();
+        let channel = state
+            .channel(self.slave_select)
+            .expect("SpiBusDevice channel not found");
+
+        self.apply(channel)?;
+
+        self.select();
+        let result = f(&mut channel.spi);
+        self.deselect();
+
+        result
+    }
+
+    /// Receives incoming data from the slave device and writes it to `buffer`.
+    ///
+    /// Returns how many bytes were read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.access(|spi| spi.read(buffer))
+    }
+
+    /// Sends the outgoing data contained in `buffer` to the slave device.
+    ///
+    /// Returns how many bytes were written.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.access(|spi| spi.write(buffer))
+    }
+
+    /// Sends and receives data at the same time.
+    ///
+    /// `transfer` will only transfer as many bytes as the shortest of the two
+    /// buffers contains.
+    ///
+    /// Returns how many bytes were transferred.
+    pub fn transfer(&mut self, read_buffer: &mut [u8], write_buffer: &[u8]) -> Result<usize> {
+        self.access(|spi| spi.transfer(read_buffer, write_buffer))
+    }
+
+    /// Transfers multiple half-duplex or full-duplex segments.
+    ///
+    /// See [`Spi::transfer_segments`] for more information.
+    ///
+    /// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+    pub fn transfer_segments(&mut self, segments: &[Segment<'_, '_>]) -> Result<()> {
+        self.access(|spi| spi.transfer_segments(segments))
+    }
+
+    // Applies this device's settings, unless it was the last device to access the channel
+    fn apply(&self, channel: &mut Channel) -> Result<()> {
+        if channel.active == Some(self.id) {
+            return Ok(());
+        }
+
+        // Clear the active device first, in case any of the settings fail
+        channel.active = None;
+
+        // The bit order is set separately, so it can fall back to software emulation
+        let mut mode = self.mode as u32;
+        if self.ss.is_some() {
+            mode |= u32::from(ioctl::MODE_NO_CS);
+        } else if self.ss_polarity == Polarity::ActiveHigh {
+            mode |= u32::from(ioctl::MODE_CS_HIGH);
+        }
+
+        match ioctl::set_mode32(channel.spi.spidev.as_raw_fd(), mode) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {
+                return Err(Error::ModeNotSupported(self.mode));
+            }
+            Err(e) => return Err(Error::Io(e)),
+        }
+
+        // Also resets any emulated LsbFirst bit order left over from the previous device
+        channel.spi.set_bit_order(self.bit_order)?;
+        channel.spi.set_clock_speed(self.clock_speed)?;
+        channel.active = Some(self.id);
+
+        Ok(())
+    }
+
+    // Makes sure the bus reapplies this device's settings on the next access
+    fn invalidate(&self) {
+        let mut state = self.bus.state.lock().unwrap();
+        if let Some(channel) = state.channel(self.slave_select) {
+            if channel.active == Some(self.id) {
+                channel.active = None;
+            }
+        }
+    }
+
+    fn select(&mut self) {
+        let level = self.ss_active();
+        if let Some(ref mut ss) = self.ss {
+            ss.write(level);
+        }
+    }
+
+    fn deselect(&mut self) {
+        let level = !self.ss_active();
+        if let Some(ref mut ss) = self.ss {
+            ss.write(level);
+        }
+    }
+
+    fn ss_active(&self) -> Level {
+        match self.ss_polarity {
+            Polarity::ActiveLow => Level::Low,
+            Polarity::ActiveHigh => Level::High,
+        }
+    }
+}
+
+impl fmt::Debug for SpiBusDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiBusDevice")
+            .field("bus", &self.bus)
+            .field("slave_select", &self.slave_select)
+            .field("ss", &self.ss)
+            .field("clock_speed", &self.clock_speed)
+            .field("mode", &self.mode)
+            .field("bit_order", &self.bit_order)
+            .field("ss_polarity", &self.ss_polarity)
+            .finish()
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::gpio::{Level, OutputPin};
+
+use super::{Polarity, Result, Segment, Spi};
+
+/// Provides access to a slave device on an SPI bus, using any available GPIO
+/// pin as its Slave Select signal.
+///
+/// The SPI buses only offer a limited number of hardware Slave Select pins,
+/// which are configured through the device tree overlays. `GpioCsDevice`
+/// configures an [`Spi`] instance with `SPI_NO_CS`, so the hardware Slave Select
+/// pin is left untouched, and drives its own [`OutputPin`] instead. This makes it
+/// possible to connect more slave devices than the bus supports natively, or to use
+/// a Slave Select pin that isn't covered by the overlay.
+///
+/// Slave Select is set to active at the start of each call, and inactive when
+/// the call completes. An optional setup delay is added after Slave Select is set
+/// to active, and an optional hold delay before it's set to inactive.
+///
+/// The `embedded-hal` `SpiDevice` trait implementation for `GpioCsDevice` can be
+/// enabled by specifying the optional `hal` feature in the dependency declaration
+/// for the `rppal` crate.
+///
+/// [`Spi`]: struct.Spi.html
+/// [`OutputPin`]: ../gpio/struct.OutputPin.html
+pub struct GpioCsDevice {
+    spi: Spi,
+    ss: OutputPin,
+    ss_polarity: Polarity,
+    setup_delay: Duration,
+    hold_delay: Duration,
+}
+
+impl GpioCsDevice {
+    /// Constructs a new `GpioCsDevice`.
+    ///
+    /// `spi` is configured with `SPI_NO_CS`, and `ss` is set to inactive.
+    ///
+    /// Returns an `io::ErrorKind::InvalidInput` error if the underlying driver
+    /// doesn't support `SPI_NO_CS`.
+    ///
+    /// By default, the Slave Select polarity is set to `ActiveLow`, and both
+    /// the setup and hold delays are set to 0.
+    pub fn new(spi: Spi, ss: OutputPin) -> Result<GpioCsDevice> {
+        spi.set_no_cs(true)?;
+
+        let mut device = GpioCsDevice {
+            spi,
+            ss,
+            ss_polarity: Polarity::ActiveLow,
+            setup_delay: Duration::default(),
+            hold_delay: Duration::default(),
+        };
+
+        device.deselect();
+
+        Ok(device)
+    }
+
+    /// Returns a reference to the underlying [`Spi`].
+    ///
+    /// [`Spi`]: struct.Spi.html
+    pub fn spi(&self) -> &Spi {
+        &self.spi
+    }
+
+    /// Consumes the `GpioCsDevice`, and returns the underlying [`Spi`] and
+    /// Slave Select pin.
+    ///
+    /// `SPI_NO_CS` is disabled before the `Spi` is returned.
+    ///
+    /// [`Spi`]: struct.Spi.html
+    pub fn into_inner(self) -> Result<(Spi, OutputPin)> {
+        self.spi.set_no_cs(false)?;
+
+        Ok((self.spi, self.ss))
+    }
+
+    /// Returns the Slave Select polarity.
+    pub fn ss_polarity(&self) -> Polarity {
+        self.ss_polarity
+    }
+
+    /// Sets the Slave Select polarity.
+    ///
+    /// By default, the Slave Select polarity is set to `ActiveLow`.
+    pub fn set_ss_polarity(&mut self, polarity: Polarity) {
+        self.ss_polarity = polarity;
+        self.deselect();
+    }
+
+    /// Returns the delay between setting Slave Select to active and the start
+    /// of the transfer.
+    pub fn setup_delay(&self) -> Duration {
+        self.setup_delay
+    }
+
+    /// Sets the delay between setting Slave Select to active and the start
+    /// of the transfer.
+    ///
+    /// By default, `setup_delay` is set to 0.
+    pub fn set_setup_delay(&mut self, setup_delay: Duration) {
+        self.setup_delay = setup_delay;
+    }
+
+    /// Returns the delay between the end of the transfer and setting Slave
+    /// Select to inactive.
+    pub fn hold_delay(&self) -> Duration {
+        self.hold_delay
+    }
+
+    /// Sets the delay between the end of the transfer and setting Slave
+    /// Select to inactive.
+    ///
+    /// By default, `hold_delay` is set to 0.
+    pub fn set_hold_delay(&mut self, hold_delay: Duration) {
+        self.hold_delay = hold_delay;
+    }
+
+    /// Receives incoming data from the slave device and writes it to `buffer`.
+    ///
+    /// Returns how many bytes were read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.select();
+        let result = self.spi.read(buffer);
+        self.deselect();
+
+        result
+    }
+
+    /// Sends the outgoing data contained in `buffer` to the slave device.
+    ///
+    /// Returns how many bytes were written.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.select();
+        let result = self.spi.write(buffer);
+        self.deselect();
+
+        result
+    }
+
+    /// Sends and receives data at the same time.
+    ///
+    /// `transfer` will only transfer as many bytes as the shortest of the two
+    /// buffers contains.
+    ///
+    /// Returns how many bytes were transferred.
+    pub fn transfer(&mut self, read_buffer: &mut [u8], write_buffer: &[u8]) -> Result<usize> {
+        self.select();
+        let result = self.spi.transfer(read_buffer, write_buffer);
+        self.deselect();
+
+        result
+    }
+
+    /// Transfers multiple half-duplex or full-duplex segments.
+    ///
+    /// Slave Select stays active until all segments have been transferred.
+    /// [`Segment::set_ss_change`] has no effect on the GPIO Slave Select pin.
+    ///
+    /// [`Segment::set_ss_change`]: struct.Segment.html#method.set_ss_change
+    pub fn transfer_segments(&mut self, segments: &[Segment<'_, '_>]) -> Result<()> {
+        self.select();
+        let result = self.spi.transfer_segments(segments);
+        self.deselect();
+
+        result
+    }
+
+    pub(crate) fn select(&mut self) {
+        self.ss.write(self.ss_active());
+        delay(self.setup_delay);
+    }
+
+    pub(crate) fn deselect(&mut self) {
+        delay(self.hold_delay);
+        self.ss.write(!self.ss_active());
+    }
+
+    fn ss_active(&self) -> Level {
+        match self.ss_polarity {
+            Polarity::ActiveLow => Level::Low,
+            Polarity::ActiveHigh => Level::High,
+        }
+    }
+}
+
+impl fmt::Debug for GpioCsDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpioCsDevice")
+            .field("spi", &self.spi)
+            .field("ss", &self.ss)
+            .field("ss_polarity", &self.ss_polarity)
+            .field("setup_delay", &self.setup_delay)
+            .field("hold_delay", &self.hold_delay)
+            .finish()
+    }
+}
+
+// Sleeps, and spins for the final part of the delay, since the delays are typically
+// too short for thread::sleep to be accurate
+pub(crate) fn delay(duration: Duration) {
+    if duration > Duration::default() {
+        spin_sleep::sleep(duration);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Error, Segment, Spi};
+use super::{gpio_cs_device, Error, GpioCsDevice, Segment, SoftSpi, Spi, SpiBusDevice};
 
 #[cfg(feature = "embedded-hal")]
 impl embedded_hal::spi::ErrorType for Spi {
@@ -8,7 +8,19 @@ impl embedded_hal::spi::ErrorType for Spi {
 #[cfg(feature = "embedded-hal")]
 impl embedded_hal::spi::Error for Error {
     fn kind(&self) -> embedded_hal::spi::ErrorKind {
-        embedded_hal::spi::ErrorKind::Other
+        use embedded_hal::spi::ErrorKind;
+
+        match self {
+            // The requested frame format can't be used with this controller
+            Error::BitsPerWordNotSupported(_)
+            | Error::BitOrderNotSupported(_)
+            | Error::ModeNotSupported(_)
+            | Error::ModeFlagsNotSupported(_)
+            | Error::WordDelayNotSupported(_) => ErrorKind::FrameFormat,
+            Error::PolarityNotSupported(_) => ErrorKind::ChipSelectFault,
+            // spidev doesn't report overruns or mode faults as distinct error codes
+            _ => ErrorKind::Other,
+        }
     }
 }
 
@@ -39,6 +51,34 @@ impl embedded_hal::spi::SpiBus<u8> for Spi {
     }
 }
 
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiBus<u16> for Spi {
+    fn read(&mut self, words: &mut [u16]) -> Result<(), Self::Error> {
+        Spi::read_u16(self, words)?;
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u16]) -> Result<(), Self::Error> {
+        Spi::write_u16(self, words)?;
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u16], write: &[u16]) -> Result<(), Self::Error> {
+        Spi::transfer_u16(self, read, write)?;
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u16]) -> Result<(), Self::Error> {
+        let write_buffer = words.to_vec();
+        Spi::transfer_u16(self, words, &write_buffer)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
 #[cfg(feature = "embedded-hal-0")]
 impl embedded_hal_0::blocking::spi::Transfer<u8> for Spi {
     type Error = Error;
@@ -150,3 +190,200 @@ impl embedded_hal::spi::SpiDevice<u8> for SimpleHalSpiDevice {
 impl embedded_hal::spi::ErrorType for SimpleHalSpiDevice {
     type Error = Error;
 }
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::ErrorType for GpioCsDevice {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiDevice<u8> for GpioCsDevice {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Error> {
+        // Keep Slave Select active until all operations have completed
+        self.select();
+        let result = spi_device_transaction(self.spi(), operations);
+        self.deselect();
+
+        result
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::ErrorType for SpiBusDevice {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiDevice<u8> for SpiBusDevice {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Error> {
+        // Hardware Slave Select is only kept active within a single transfer
+        if self.slave_select().is_some() {
+            self.access(|spi| spi_device_segments(spi, operations))
+        } else {
+            self.access(|spi| spi_device_transaction(spi, operations))
+        }
+    }
+}
+
+// Transfers all operations as segments of a single transfer, so hardware Slave Select
+// stays active
+#[cfg(feature = "embedded-hal")]
+fn spi_device_segments(
+    spi: &Spi,
+    operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+) -> Result<(), Error> {
+    // TransferInPlace needs a copy of the outgoing data that outlives the segments
+    let write_buffers: Vec<Vec<u8>> = operations
+        .iter()
+        .map(|op| match op {
+            embedded_hal::spi::Operation::TransferInPlace(buff) => buff.to_vec(),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let segments = operations
+        .iter_mut()
+        .zip(&write_buffers)
+        .map(|(op, write_copy)| match op {
+            embedded_hal::spi::Operation::Read(read_buff) => Segment::with_read(read_buff),
+            embedded_hal::spi::Operation::Write(write_buff) => Segment::with_write(write_buff),
+            embedded_hal::spi::Operation::Transfer(read_buff, write_buff) => {
+                Segment::new(read_buff, write_buff)
+            }
+            embedded_hal::spi::Operation::TransferInPlace(buff) => Segment::new(buff, write_copy),
+            // A segment without any buffers only adds a delay
+            embedded_hal::spi::Operation::DelayNs(delay_ns) => {
+                let delay_us = (u64::from(*delay_ns) + 999) / 1000;
+                let mut segment = Segment::with_settings(None, None, 0, 0, 0, false);
+                segment.set_delay(delay_us.min(u64::from(u16::MAX)) as u16);
+
+                segment
+            }
+        })
+        .collect::<Vec<Segment<'_, '_>>>();
+
+    spi.transfer_segments(&segments)
+}
+
+// Transfers each operation separately, while the caller keeps Slave Select active
+#[cfg(feature = "embedded-hal")]
+fn spi_device_transaction(
+    spi: &Spi,
+    operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+) -> Result<(), Error> {
+    for op in operations {
+        match op {
+            embedded_hal::spi::Operation::Read(read_buff) => {
+                spi.transfer_segments(&[Segment::with_read(read_buff)])?;
+            }
+            embedded_hal::spi::Operation::Write(write_buff) => {
+                spi.transfer_segments(&[Segment::with_write(write_buff)])?;
+            }
+            embedded_hal::spi::Operation::Transfer(read_buff, write_buff) => {
+                spi.transfer_segments(&[Segment::new(read_buff, write_buff)])?;
+            }
+            embedded_hal::spi::Operation::TransferInPlace(buff) => {
+                let write_buff = buff.to_vec();
+                spi.transfer_segments(&[Segment::new(buff, &write_buff)])?;
+            }
+            embedded_hal::spi::Operation::DelayNs(delay_ns) => {
+                gpio_cs_device::delay(std::time::Duration::from_nanos((*delay_ns).into()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::ErrorType for SoftSpi {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiBus<u8> for SoftSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        SoftSpi::read(self, words)?;
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        SoftSpi::write(self, words)?;
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        SoftSpi::transfer(self, read, write)?;
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        SoftSpi::transfer_in_place(self, buffer)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiDevice<u8> for SoftSpi {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Error> {
+        // Keep Slave Select active until all operations have completed
+        self.select();
+
+        for op in operations {
+            match op {
+                embedded_hal::spi::Operation::Read(read_buff) => {
+                    self.shift(Some(read_buff), None);
+                }
+                embedded_hal::spi::Operation::Write(write_buff) => {
+                    self.shift(None, Some(write_buff));
+                }
+                embedded_hal::spi::Operation::Transfer(read_buff, write_buff) => {
+                    self.shift(Some(read_buff), Some(write_buff));
+                }
+                embedded_hal::spi::Operation::TransferInPlace(buff) => {
+                    self.shift_in_place(buff);
+                }
+                embedded_hal::spi::Operation::DelayNs(delay_ns) => {
+                    spin_sleep::sleep(std::time::Duration::from_nanos((*delay_ns).into()));
+                }
+            }
+        }
+
+        self.deselect();
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::spi::Transfer<u8> for SoftSpi {
+    type Error = Error;
+
+    fn transfer<'a>(&mut self, buffer: &'a mut [u8]) -> Result<&'a [u8], Self::Error> {
+        SoftSpi::transfer_in_place(self, buffer)?;
+        Ok(buffer)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::spi::Write<u8> for SoftSpi {
+    type Error = Error;
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), Self::Error> {
+        SoftSpi::write(self, buffer)?;
+        Ok(())
+    }
+}
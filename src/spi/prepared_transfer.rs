@@ -0,0 +1,267 @@
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::time::Duration;
+
+use libc::c_void;
+
+use super::{Result, Segment};
+
+// Fallback in case the page size can't be retrieved
+const PAGE_SIZE_DEFAULT: usize = 4096;
+
+// Locations of the buffers owned by a single prepared segment
+struct Buffers {
+    read: Option<Range<usize>>,
+    write: Option<Range<usize>>,
+}
+
+// Page-aligned memory that isn't shared with any other allocations, which makes
+// it safe to lock and unlock with mlock/munlock.
+struct Pages {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Pages {
+    fn new(len: usize) -> Pages {
+        let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as usize,
+            _ => PAGE_SIZE_DEFAULT,
+        };
+
+        // Round up to whole pages. Always allocate at least one page, since
+        // zero-sized allocations aren't allowed.
+        let size = ((len.max(1) + page_size - 1) / page_size) * page_size;
+        let layout =
+            Layout::from_size_align(size, page_size).expect("Invalid PreparedTransfer layout");
+
+        let ptr = match NonNull::new(unsafe { alloc::alloc_zeroed(layout) }) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        Pages { ptr, layout }
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        unsafe {
+            alloc::dealloc(self.ptr.as_ptr(), self.layout);
+        }
+    }
+}
+
+/// A reusable multi-segment transfer.
+///
+/// [`Spi::transfer_segments`] takes a slice of [`Segment`]s that reference
+/// buffers owned by the caller. `PreparedTransfer` copies the settings of each
+/// segment once, allocates its own read and write buffers, and keeps the
+/// underlying `spi_ioc_transfer` structures ready to be passed to the `spidev`
+/// driver with [`Spi::transfer_prepared`].
+///
+/// Outgoing data can be changed in between transfers through [`write_buffer_mut`].
+/// Incoming data is available through [`read_buffer`] after each transfer.
+///
+/// Optionally, all memory used during a transfer can be locked into RAM with
+/// [`lock_memory`], which prevents page faults during a transfer. This is the
+/// main benefit of a `PreparedTransfer`. For transfers that fit within
+/// [`Spi::max_transfer_size`], `transfer_segments` doesn't allocate any memory
+/// either, so the average time per transfer is roughly the same.
+///
+/// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+/// [`Spi::transfer_prepared`]: struct.Spi.html#method.transfer_prepared
+/// [`Spi::max_transfer_size`]: struct.Spi.html#method.max_transfer_size
+/// [`Segment`]: struct.Segment.html
+/// [`write_buffer_mut`]: #method.write_buffer_mut
+/// [`read_buffer`]: #method.read_buffer
+/// [`lock_memory`]: #method.lock_memory
+pub struct PreparedTransfer {
+    // Contains the segments, followed by all read and write buffers. The segments
+    // reference the buffers, and are never exposed with a 'static lifetime, so
+    // they can't outlive the allocation.
+    pages: Pages,
+    segment_count: usize,
+    buffers: Vec<Buffers>,
+    locked: bool,
+}
+
+impl PreparedTransfer {
+    /// Constructs a new `PreparedTransfer` based on `segments`.
+    ///
+    /// The settings and outgoing data of each segment are copied. For every
+    /// segment with a read buffer, a zero-initialized buffer of the same length is
+    /// allocated. The contents of the original read buffers aren't used.
+    pub fn new(segments: &[Segment<'_, '_>]) -> PreparedTransfer {
+        // The segments are stored at the start of the allocation, which is page-aligned
+        let mut offset = mem::size_of_val(segments);
+        let mut take = |len: usize| {
+            let range = offset..offset + len;
+            offset += len;
+
+            range
+        };
+
+        let buffers: Vec<Buffers> = segments
+            .iter()
+            .map(|segment| Buffers {
+                read: if segment.has_read_buffer() {
+                    Some(take(segment.len()))
+                } else {
+                    None
+                },
+                write: segment.write_buffer().map(|_| take(segment.len())),
+            })
+            .collect();
+
+        let pages = Pages::new(offset);
+        let base = pages.ptr.as_ptr();
+
+        for (index, (segment, buffers)) in segments.iter().zip(&buffers).enumerate() {
+            // Safe because all ranges are located within the allocation, and don't overlap
+            let read = buffers.read.as_ref().map(|range| unsafe {
+                slice::from_raw_parts_mut(base.add(range.start), range.len())
+            });
+            let write = buffers.write.as_ref().map(|range| unsafe {
+                let buffer = slice::from_raw_parts_mut(base.add(range.start), range.len());
+                buffer.copy_from_slice(&segment.write_buffer().unwrap_or(&[])[..range.len()]);
+
+                &*buffer
+            });
+
+            unsafe {
+                ptr::write(
+                    (base as *mut Segment<'static, 'static>).add(index),
+                    segment.with_buffers(read, write),
+                );
+            }
+        }
+
+        PreparedTransfer {
+            pages,
+            segment_count: segments.len(),
+            buffers,
+            locked: false,
+        }
+    }
+
+    /// Returns the number of segments.
+    pub fn len(&self) -> usize {
+        self.segment_count
+    }
+
+    /// Returns `true` if the transfer doesn't contain any segments.
+    pub fn is_empty(&self) -> bool {
+        self.segment_count == 0
+    }
+
+    /// Returns the incoming data for the segment at `index`, as received
+    /// during the last transfer.
+    ///
+    /// Returns `None` if `index` is out of bounds, or if the segment doesn't
+    /// have a read buffer.
+    pub fn read_buffer(&self, index: usize) -> Option<&[u8]> {
+        let range = self.buffers.get(index)?.read.as_ref()?;
+
+        // Safe because the range is located within the allocation
+        Some(unsafe {
+            slice::from_raw_parts(self.pages.ptr.as_ptr().add(range.start), range.len())
+        })
+    }
+
+    /// Returns a mutable reference to the outgoing data for the segment at `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds, or if the segment doesn't
+    /// have a write buffer.
+    pub fn write_buffer_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        let range = self.buffers.get(index)?.write.as_ref()?;
+
+        // Safe because the range is located within the allocation, and we're
+        // exclusively borrowed
+        Some(unsafe {
+            slice::from_raw_parts_mut(self.pages.ptr.as_ptr().add(range.start), range.len())
+        })
+    }
+
+    /// Locks all memory used during a transfer into RAM using `mlock`.
+    ///
+    /// Locked memory can't be paged out, which avoids page faults during a
+    /// transfer. The segments and buffers are stored in their own page-aligned
+    /// allocation, which is unlocked when the `PreparedTransfer` is dropped,
+    /// without affecting any other locked memory. If the process has locked all
+    /// of its memory with `mlockall`, calling `lock_memory` isn't needed.
+    ///
+    /// The amount of memory a process can lock is limited by `RLIMIT_MEMLOCK`.
+    /// If the limit is exceeded, an `io::ErrorKind::OutOfMemory` or
+    /// `io::ErrorKind::PermissionDenied` error is returned.
+    pub fn lock_memory(&mut self) -> Result<()> {
+        if self.locked {
+            return Ok(());
+        }
+
+        parse_retval!(unsafe {
+            libc::mlock(
+                self.pages.ptr.as_ptr() as *const c_void,
+                self.pages.layout.size(),
+            )
+        })?;
+
+        self.locked = true;
+
+        Ok(())
+    }
+
+    /// Returns `true` if the memory used during a transfer is locked into RAM.
+    pub fn is_memory_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment<'_, '_>] {
+        // Safe because the segments were initialized in new()
+        unsafe {
+            slice::from_raw_parts(
+                self.pages.ptr.as_ptr() as *const Segment<'_, '_>,
+                self.segment_count,
+            )
+        }
+    }
+
+    pub(crate) fn has_word_delay(&self) -> bool {
+        self.segments()
+            .iter()
+            .any(|segment| segment.word_delay() > Duration::default())
+    }
+}
+
+impl Drop for PreparedTransfer {
+    fn drop(&mut self) {
+        // The pages aren't shared with any other allocations, so unlocking them
+        // doesn't affect memory locked elsewhere
+        if self.locked {
+            unsafe {
+                libc::munlock(
+                    self.pages.ptr.as_ptr() as *const c_void,
+                    self.pages.layout.size(),
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PreparedTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedTransfer")
+            .field("segments", &self.segments())
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+// Send and Sync are safe for PreparedTransfer, since it exclusively owns its
+// allocation, and only hands out references to it through &self and &mut self.
+unsafe impl Send for PreparedTransfer {}
+unsafe impl Sync for PreparedTransfer {}
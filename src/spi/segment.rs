@@ -1,5 +1,9 @@
 use std::fmt;
 use std::marker;
+use std::slice;
+use std::time::Duration;
+
+use super::{Error, Result};
 
 /// Part of a multi-segment transfer.
 ///
@@ -10,10 +14,19 @@ use std::marker;
 /// use [`with_read`]. For write operations where any incoming data
 /// should be discarded, use [`with_write`].
 ///
+/// The `spidev` interface doesn't expose the `cs_change_delay` and `cs_off`
+/// settings that are available to kernel drivers. When Slave Select is toggled
+/// in between two segments using [`set_ss_change`], the driver keeps it inactive
+/// for its default duration, which is typically 10 µs. If your slave device
+/// needs a longer Slave Select inactive time, split the segments over multiple
+/// [`Spi::transfer_segments`] calls, or use a [`GpioCsDevice`].
+///
 /// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
 /// [`with_read`]: #method.with_read
 /// [`with_write`]: #method.with_write
 /// [`new`]: #method.new
+/// [`set_ss_change`]: #method.set_ss_change
+/// [`GpioCsDevice`]: struct.GpioCsDevice.html
 #[derive(PartialEq, Eq, Copy, Clone)]
 #[repr(C)]
 pub struct Segment<'a, 'b> {
@@ -31,12 +44,14 @@ pub struct Segment<'a, 'b> {
     bits_per_word: u8,
     // Set to 1 to briefly set SS inactive between this segment and the next. If this is the last segment, keep SS active.
     cs_change: u8,
-    // Number of outgoing lines used for dual/quad SPI. Not supported on the Raspberry Pi. Default = 0.
+    // Number of outgoing lines used for dual/quad SPI. Requires SPI_TX_DUAL or SPI_TX_QUAD. Default = 0.
     tx_nbits: u8,
-    // Number of incoming lines used for dual/quad SPI. Not supported on the Raspberry Pi. Default = 0.
+    // Number of incoming lines used for dual/quad SPI. Requires SPI_RX_DUAL or SPI_RX_QUAD. Default = 0.
     rx_nbits: u8,
+    // Delay between words within this segment. Requires Linux 5.3 or later. Default = 0.
+    word_delay_usecs: u8,
     // Padding. Set to 0 for forward compatibility.
-    pad: u16,
+    pad: u8,
     // Zero-sized variable used to link this struct to the read buffer lifetime.
     read_buffer_lifetime: marker::PhantomData<&'a mut [u8]>,
     // Zero-sized variable used to link this struct to the write buffer lifetime.
@@ -153,6 +168,7 @@ impl<'a, 'b> Segment<'a, 'b> {
             cs_change: ss_change as u8,
             tx_nbits: 0,
             rx_nbits: 0,
+            word_delay_usecs: 0,
             pad: 0,
             read_buffer_lifetime: marker::PhantomData,
             write_buffer_lifetime: marker::PhantomData,
@@ -241,6 +257,149 @@ impl<'a, 'b> Segment<'a, 'b> {
     pub fn set_ss_change(&mut self, ss_change: bool) {
         self.cs_change = ss_change as u8;
     }
+
+    /// Gets the delay between words for this segment.
+    pub fn word_delay(&self) -> Duration {
+        Duration::from_micros(u64::from(self.word_delay_usecs))
+    }
+
+    /// Sets a delay between words for this segment.
+    ///
+    /// `set_word_delay` adds a delay after every word that's transferred,
+    /// which gives slow slave devices, such as some ADCs, time to process
+    /// each word. The delay is rounded up to whole microseconds (µs).
+    ///
+    /// Returns [`Error::WordDelayNotSupported`] if `word_delay` exceeds 255 µs.
+    /// Word delays require Linux 5.3 or later. On older kernels,
+    /// [`Spi::transfer_segments`] returns [`Error::WordDelayNotSupported`]
+    /// for any segment with a non-zero word delay.
+    ///
+    /// By default, `word_delay` is set to `0`.
+    ///
+    /// [`Error::WordDelayNotSupported`]: enum.Error.html#variant.WordDelayNotSupported
+    /// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+    pub fn set_word_delay(&mut self, word_delay: Duration) -> Result<()> {
+        let micros = (word_delay.as_nanos() + 999) / 1000;
+        if micros > u128::from(u8::MAX) {
+            return Err(Error::WordDelayNotSupported(word_delay));
+        }
+
+        self.word_delay_usecs = micros as u8;
+
+        Ok(())
+    }
+
+    /// Gets the number of outgoing data lines for this segment.
+    pub fn tx_nbits(&self) -> u8 {
+        self.tx_nbits
+    }
+
+    /// Sets the number of outgoing data lines for this segment.
+    ///
+    /// `tx_nbits` can be set to `1` (single), `2` (dual) or `4` (quad). Dual
+    /// and quad transfers are half-duplex, and require the corresponding
+    /// [`ModeFlags`] to be enabled through [`Spi::set_mode_flags`]. If the
+    /// flags aren't enabled, [`Spi::transfer_segments`] returns an
+    /// `io::ErrorKind::InvalidInput` error.
+    ///
+    /// By default, `tx_nbits` is set to `0`, which means
+    /// it will use a single data line.
+    ///
+    /// [`ModeFlags`]: struct.ModeFlags.html
+    /// [`Spi::set_mode_flags`]: struct.Spi.html#method.set_mode_flags
+    /// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+    pub fn set_tx_nbits(&mut self, tx_nbits: u8) {
+        self.tx_nbits = tx_nbits;
+    }
+
+    /// Gets the number of incoming data lines for this segment.
+    pub fn rx_nbits(&self) -> u8 {
+        self.rx_nbits
+    }
+
+    /// Sets the number of incoming data lines for this segment.
+    ///
+    /// `rx_nbits` can be set to `1` (single), `2` (dual) or `4` (quad). Dual
+    /// and quad transfers are half-duplex, and require the corresponding
+    /// [`ModeFlags`] to be enabled through [`Spi::set_mode_flags`]. If the
+    /// flags aren't enabled, [`Spi::transfer_segments`] returns an
+    /// `io::ErrorKind::InvalidInput` error.
+    ///
+    /// By default, `rx_nbits` is set to `0`, which means
+    /// it will use a single data line.
+    ///
+    /// [`ModeFlags`]: struct.ModeFlags.html
+    /// [`Spi::set_mode_flags`]: struct.Spi.html#method.set_mode_flags
+    /// [`Spi::transfer_segments`]: struct.Spi.html#method.transfer_segments
+    pub fn set_rx_nbits(&mut self, rx_nbits: u8) {
+        self.rx_nbits = rx_nbits;
+    }
+
+    // Returns the write buffer, if any
+    pub(crate) fn write_buffer(&self) -> Option<&'b [u8]> {
+        if self.tx_buf == 0 {
+            return None;
+        }
+
+        // Safe because tx_buf points to a buffer borrowed for 'b that contains at least len bytes
+        Some(unsafe { slice::from_raw_parts(self.tx_buf as *const u8, self.len as usize) })
+    }
+
+    // Returns a copy of this segment's settings, using the specified buffers instead.
+    // Both buffers must contain at least len bytes.
+    pub(crate) fn with_buffers<'c, 'd>(
+        &self,
+        read_buffer: Option<&'c mut [u8]>,
+        write_buffer: Option<&'d [u8]>,
+    ) -> Segment<'c, 'd> {
+        assert!(read_buffer
+            .as_ref()
+            .map_or(true, |b| b.len() >= self.len as usize));
+        assert!(write_buffer.map_or(true, |b| b.len() >= self.len as usize));
+
+        Segment {
+            tx_buf: write_buffer.map_or(0, |buffer| buffer.as_ptr() as u64),
+            rx_buf: read_buffer.map_or(0, |buffer| buffer.as_mut_ptr() as u64),
+            read_buffer_lifetime: marker::PhantomData,
+            write_buffer_lifetime: marker::PhantomData,
+            ..*self
+        }
+    }
+
+    // Returns true if this segment has a read buffer
+    pub(crate) fn has_read_buffer(&self) -> bool {
+        self.rx_buf != 0
+    }
+
+    // Returns the read buffer, if any. The caller needs to make sure the buffer
+    // isn't accessed through any other references.
+    pub(crate) unsafe fn read_buffer(&self) -> Option<&'a mut [u8]> {
+        if self.rx_buf == 0 {
+            return None;
+        }
+
+        Some(slice::from_raw_parts_mut(
+            self.rx_buf as *mut u8,
+            self.len as usize,
+        ))
+    }
+
+    // Returns a segment covering len bytes starting at offset, with the same settings
+    pub(crate) fn chunk(&self, offset: usize, len: usize) -> Segment<'a, 'b> {
+        let mut chunk = *self;
+
+        if chunk.tx_buf != 0 {
+            chunk.tx_buf += offset as u64;
+        }
+
+        if chunk.rx_buf != 0 {
+            chunk.rx_buf += offset as u64;
+        }
+
+        chunk.len = len as u32;
+
+        chunk
+    }
 }
 
 impl fmt::Debug for Segment<'_, '_> {
@@ -255,6 +414,7 @@ impl fmt::Debug for Segment<'_, '_> {
             .field("cs_change", &self.cs_change)
             .field("tx_nbits", &self.tx_nbits)
             .field("rx_nbits", &self.rx_nbits)
+            .field("word_delay_usecs", &self.word_delay_usecs)
             .field("pad", &self.pad)
             .finish()
     }
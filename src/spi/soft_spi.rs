@@ -0,0 +1,399 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::gpio::{InputPin, Level, OutputPin};
+
+use super::{BitOrder, Error, Mode, Polarity, Result};
+
+// Highest number of bits per word that fits in the largest (32-bit) word size
+const BITS_PER_WORD_MAX: u8 = 32;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Provides a bit-banged SPI master on arbitrary GPIO pins.
+///
+/// `SoftSpi` emulates an SPI bus by toggling the clock, MOSI and Slave Select
+/// pins in software, and sampling MISO on the appropriate clock edge. This can
+/// be used to add an SPI bus on pins that aren't covered by any of the SPI
+/// device tree overlays.
+///
+/// Unlike [`Spi`], `SoftSpi` supports all 4 SPI modes, both bit orders, and
+/// any number of bits per word between 1 and 32.
+///
+/// Words are stored in the read and write buffers the same way `spidev`
+/// expects them. Words of up to 8 bits use a single byte, words of 9 to 16
+/// bits use two bytes, and words of 17 to 32 bits use four bytes, in the
+/// host's native byte order. Any bytes left over at the end of a buffer that
+/// don't make up a complete word are ignored.
+///
+/// The clock speed is an upper limit. Due to function call overhead and
+/// scheduling/preemption, the actual clock frequency will be lower and
+/// may vary during a transfer.
+///
+/// The `embedded-hal` trait implementations for `SoftSpi` can be enabled by specifying
+/// the optional `hal` feature in the dependency declaration for the `rppal` crate.
+///
+/// [`Spi`]: struct.Spi.html
+pub struct SoftSpi {
+    sclk: OutputPin,
+    mosi: OutputPin,
+    miso: InputPin,
+    ss: OutputPin,
+    clock_speed: u32,
+    half_period: Duration,
+    mode: Mode,
+    bit_order: BitOrder,
+    bits_per_word: u8,
+    ss_polarity: Polarity,
+}
+
+impl SoftSpi {
+    /// Constructs a new `SoftSpi`.
+    ///
+    /// `sclk`, `mosi`, `miso` and `ss` specify the pins used for the clock,
+    /// outgoing data, incoming data and Slave Select signals.
+    ///
+    /// `clock_speed` defines the maximum clock frequency in hertz (Hz).
+    ///
+    /// `mode` selects the clock polarity and phase.
+    ///
+    /// By default, the bit order is set to `MsbFirst`, `bits_per_word` is set to 8,
+    /// and the Slave Select polarity is set to `ActiveLow`.
+    pub fn new(
+        sclk: OutputPin,
+        mosi: OutputPin,
+        miso: InputPin,
+        ss: OutputPin,
+        clock_speed: u32,
+        mode: Mode,
+    ) -> Result<SoftSpi> {
+        let mut spi = SoftSpi {
+            sclk,
+            mosi,
+            miso,
+            ss,
+            clock_speed: 0,
+            half_period: Duration::default(),
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            bits_per_word: 8,
+            ss_polarity: Polarity::ActiveLow,
+        };
+
+        spi.set_clock_speed(clock_speed)?;
+        spi.set_mode(mode);
+        spi.set_ss_polarity(Polarity::ActiveLow);
+        spi.mosi.set_low();
+
+        Ok(spi)
+    }
+
+    /// Gets the bit order.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Sets the order in which bits are shifted out and in.
+    ///
+    /// By default, `bit_order` is set to `MsbFirst`.
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+
+    /// Gets the number of bits per word.
+    pub fn bits_per_word(&self) -> u8 {
+        self.bits_per_word
+    }
+
+    /// Sets the number of bits per word.
+    ///
+    /// `bits_per_word` can be set to any value between 1 and 32.
+    ///
+    /// By default, `bits_per_word` is set to 8.
+    pub fn set_bits_per_word(&mut self, bits_per_word: u8) -> Result<()> {
+        if bits_per_word == 0 || bits_per_word > BITS_PER_WORD_MAX {
+            return Err(Error::BitsPerWordNotSupported(bits_per_word));
+        }
+
+        self.bits_per_word = bits_per_word;
+
+        Ok(())
+    }
+
+    /// Gets the maximum clock frequency in hertz (Hz).
+    pub fn clock_speed(&self) -> u32 {
+        self.clock_speed
+    }
+
+    /// Sets the maximum clock frequency in hertz (Hz).
+    pub fn set_clock_speed(&mut self, clock_speed: u32) -> Result<()> {
+        if clock_speed == 0 {
+            return Err(Error::ClockSpeedNotSupported(clock_speed));
+        }
+
+        self.clock_speed = clock_speed;
+        self.half_period = Duration::from_nanos(NANOS_PER_SEC / (u64::from(clock_speed) * 2));
+
+        Ok(())
+    }
+
+    /// Gets the SPI mode.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets the SPI mode.
+    ///
+    /// The SPI mode indicates the serial clock polarity and phase. The clock
+    /// pin is immediately changed to its idle state for the selected mode.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.sclk.write(self.clock_idle());
+    }
+
+    /// Gets the Slave Select polarity.
+    pub fn ss_polarity(&self) -> Polarity {
+        self.ss_polarity
+    }
+
+    /// Sets Slave Select polarity.
+    ///
+    /// The Slave Select pin is immediately changed to its inactive state for
+    /// the selected polarity.
+    ///
+    /// By default, the Slave Select polarity is set to `ActiveLow`.
+    pub fn set_ss_polarity(&mut self, polarity: Polarity) {
+        self.ss_polarity = polarity;
+        self.ss.write(!self.ss_active());
+    }
+
+    /// Receives incoming data from the slave device and writes it to `buffer`.
+    ///
+    /// During the read, a zero value word is shifted out on the MOSI pin for
+    /// every word received on the MISO pin.
+    ///
+    /// Slave Select is set to active at the start of the read, and inactive
+    /// when the read completes.
+    ///
+    /// Returns how many bytes were read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.select();
+        let len = self.shift(Some(buffer), None);
+        self.deselect();
+
+        Ok(len)
+    }
+
+    /// Sends the outgoing data contained in `buffer` to the slave device.
+    ///
+    /// Any data received on the MISO pin is ignored.
+    ///
+    /// Slave Select is set to active at the start of the write, and inactive
+    /// when the write completes.
+    ///
+    /// Returns how many bytes were written.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize> {
+        self.select();
+        let len = self.shift(None, Some(buffer));
+        self.deselect();
+
+        Ok(len)
+    }
+
+    /// Sends and receives data at the same time.
+    ///
+    /// `transfer` stores the incoming data in `read_buffer`, and sends the
+    /// outgoing data contained in `write_buffer`. Because data is sent and
+    /// received simultaneously, `transfer` will only transfer as many bytes as
+    /// the shortest of the two buffers contains.
+    ///
+    /// Slave Select is set to active at the start of the transfer, and inactive
+    /// when the transfer completes.
+    ///
+    /// Returns how many bytes were transferred.
+    pub fn transfer(&mut self, read_buffer: &mut [u8], write_buffer: &[u8]) -> Result<usize> {
+        self.select();
+        let len = self.shift(Some(read_buffer), Some(write_buffer));
+        self.deselect();
+
+        Ok(len)
+    }
+
+    /// Sends the outgoing data contained in `buffer`, and replaces it with
+    /// the incoming data.
+    ///
+    /// Slave Select is set to active at the start of the transfer, and inactive
+    /// when the transfer completes.
+    ///
+    /// Returns how many bytes were transferred.
+    pub fn transfer_in_place(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        self.select();
+        let len = self.shift_in_place(buffer);
+        self.deselect();
+
+        Ok(len)
+    }
+
+    // Sets Slave Select to active
+    pub(crate) fn select(&mut self) {
+        self.ss.write(self.ss_active());
+    }
+
+    // Sets Slave Select to inactive
+    pub(crate) fn deselect(&mut self) {
+        self.ss.write(!self.ss_active());
+    }
+
+    // Transfers as many complete words as fit in the shortest of the supplied
+    // buffers, without changing Slave Select. Returns the number of bytes transferred.
+    pub(crate) fn shift(
+        &mut self,
+        mut read_buffer: Option<&mut [u8]>,
+        write_buffer: Option<&[u8]>,
+    ) -> usize {
+        let word_size = self.word_size();
+
+        let len = match (&read_buffer, write_buffer) {
+            (Some(read), Some(write)) => read.len().min(write.len()),
+            (Some(read), None) => read.len(),
+            (None, Some(write)) => write.len(),
+            (None, None) => 0,
+        };
+        let len = len - (len % word_size);
+
+        for offset in (0..len).step_by(word_size) {
+            let word =
+                write_buffer.map_or(0, |buffer| load_word(&buffer[offset..offset + word_size]));
+
+            let word = self.shift_word(word);
+
+            if let Some(ref mut buffer) = read_buffer {
+                store_word(&mut buffer[offset..offset + word_size], word);
+            }
+        }
+
+        len
+    }
+
+    // Transfers as many complete words as fit in buffer, replacing the outgoing
+    // data with the incoming data. Returns the number of bytes transferred.
+    pub(crate) fn shift_in_place(&mut self, buffer: &mut [u8]) -> usize {
+        let word_size = self.word_size();
+        let len = buffer.len() - (buffer.len() % word_size);
+
+        for chunk in buffer[..len].chunks_exact_mut(word_size) {
+            let word = self.shift_word(load_word(chunk));
+            store_word(chunk, word);
+        }
+
+        len
+    }
+
+    // Shifts out a single word on MOSI while shifting in a word on MISO
+    fn shift_word(&mut self, word: u32) -> u32 {
+        let bits = u32::from(self.bits_per_word);
+        let idle = self.clock_idle();
+        let cpha = (self.mode as u8 & 0x01) != 0;
+        let mut incoming: u32 = 0;
+
+        for i in 0..bits {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => bits - 1 - i,
+                BitOrder::LsbFirst => i,
+            };
+
+            let outgoing = Level::from((word >> bit) & 0x01 != 0);
+
+            let sample = if cpha {
+                // Data is shifted out on the leading edge, and sampled on the trailing edge
+                self.sclk.write(!idle);
+                self.mosi.write(outgoing);
+                self.wait();
+                self.sclk.write(idle);
+                let sample = self.miso.read();
+                self.wait();
+
+                sample
+            } else {
+                // Data is shifted out before the leading edge, and sampled on the leading edge
+                self.mosi.write(outgoing);
+                self.wait();
+                self.sclk.write(!idle);
+                let sample = self.miso.read();
+                self.wait();
+                self.sclk.write(idle);
+
+                sample
+            };
+
+            if sample == Level::High {
+                incoming |= 1 << bit;
+            }
+        }
+
+        incoming
+    }
+
+    // Busy-waits for half a clock period
+    #[inline(always)]
+    fn wait(&self) {
+        let start = Instant::now();
+        while start.elapsed() < self.half_period {}
+    }
+
+    // Clock level while idle, based on CPOL
+    fn clock_idle(&self) -> Level {
+        Level::from((self.mode as u8 & 0x02) != 0)
+    }
+
+    // Slave Select level while active
+    fn ss_active(&self) -> Level {
+        match self.ss_polarity {
+            Polarity::ActiveLow => Level::Low,
+            Polarity::ActiveHigh => Level::High,
+        }
+    }
+
+    // Number of bytes used to store a single word
+    fn word_size(&self) -> usize {
+        match self.bits_per_word {
+            0..=8 => 1,
+            9..=16 => 2,
+            _ => 4,
+        }
+    }
+}
+
+impl fmt::Debug for SoftSpi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftSpi")
+            .field("sclk", &self.sclk.pin())
+            .field("mosi", &self.mosi.pin())
+            .field("miso", &self.miso.pin())
+            .field("ss", &self.ss.pin())
+            .field("clock_speed", &self.clock_speed)
+            .field("mode", &self.mode)
+            .field("bit_order", &self.bit_order)
+            .field("bits_per_word", &self.bits_per_word)
+            .field("ss_polarity", &self.ss_polarity)
+            .finish()
+    }
+}
+
+// Reads a 1, 2 or 4-byte word in native byte order
+fn load_word(bytes: &[u8]) -> u32 {
+    match bytes.len() {
+        1 => u32::from(bytes[0]),
+        2 => u32::from(u16::from_ne_bytes([bytes[0], bytes[1]])),
+        _ => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
+
+// Writes a 1, 2 or 4-byte word in native byte order
+fn store_word(bytes: &mut [u8], word: u32) {
+    match bytes.len() {
+        1 => bytes[0] = word as u8,
+        2 => bytes.copy_from_slice(&(word as u16).to_ne_bytes()),
+        _ => bytes.copy_from_slice(&word.to_ne_bytes()),
+    }
+}
@@ -11,6 +11,8 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::result;
 
+pub(crate) mod devicetree;
+
 // Peripheral base address
 const PERIPHERAL_BASE_RPI: u32 = 0x2000_0000;
 const PERIPHERAL_BASE_RPI2: u32 = 0x3f00_0000;
@@ -483,4 +485,19 @@ impl DeviceInfo {
     pub(crate) fn pwm_chip(&self) -> u8 {
         self.pwm_chip
     }
+
+    /// Returns the BCM GPIO numbers of the SDA and SCL pins for the specified
+    /// I2C bus in its default configuration.
+    pub(crate) fn i2c_pins(&self, bus: u8) -> Option<(u8, u8)> {
+        match bus {
+            0 => Some((0, 1)),
+            1 => Some((2, 3)),
+            3..=6 if self.soc != SoC::Bcm2711 && self.soc != SoC::Bcm2712 => None,
+            3 => Some((4, 5)),
+            4 => Some((8, 9)),
+            5 => Some((12, 13)),
+            6 => Some((22, 23)),
+            _ => None,
+        }
+    }
 }
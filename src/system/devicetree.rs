@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const DEVICETREE_BASE: &str = "/sys/firmware/devicetree/base";
+
+// Checks whether the device tree node referenced by the of_node symlink in
+// device_path matches name. name can either be the full node name including its
+// unit address (i2c@7e804000), or a path relative to the device tree root
+// (soc/i2c@7e804000).
+pub(crate) fn node_matches(device_path: &Path, name: &str) -> bool {
+    let node = match fs::canonicalize(device_path.join("of_node")) {
+        Ok(node) => node,
+        Err(_) => return false,
+    };
+
+    let name = name.trim_matches('/');
+    if name.contains('/') {
+        node.ends_with(name)
+    } else {
+        node.file_name()
+            .map_or(false, |file_name| file_name == name)
+    }
+}
+
+// Returns the BCM GPIO numbers of all pins assigned to the device tree node
+// referenced by the of_node symlink in device_path, based on its default
+// pinctrl state.
+pub(crate) fn node_pins(device_path: &Path) -> io::Result<Vec<u8>> {
+    let phandles = read_u32_cells(&device_path.join("of_node").join("pinctrl-0"))?;
+    let nodes = phandle_map()?;
+
+    let mut pins = Vec::new();
+    for phandle in phandles {
+        if let Some(node) = nodes.get(&phandle) {
+            collect_pins(node, &mut pins);
+
+            // Some pin controllers split the configuration into subnodes
+            if let Ok(entries) = fs::read_dir(node) {
+                for entry in entries.flatten() {
+                    if entry.path().is_dir() {
+                        collect_pins(&entry.path(), &mut pins);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(pins)
+}
+
+fn collect_pins(node: &Path, pins: &mut Vec<u8>) {
+    // BCM283x/BCM2711 pin controller
+    if let Ok(cells) = read_u32_cells(&node.join("brcm,pins")) {
+        pins.extend(cells.into_iter().filter_map(|pin| u8::try_from(pin).ok()));
+    }
+
+    // RP1 pin controller lists its pins as strings (gpio2, gpio3, ...)
+    if let Ok(data) = fs::read(node.join("pins")) {
+        pins.extend(
+            data.split(|&b| b == 0)
+                .filter_map(|name| std::str::from_utf8(name).ok())
+                .filter_map(|name| name.strip_prefix("gpio"))
+                .filter_map(|pin| pin.parse::<u8>().ok()),
+        );
+    }
+}
+
+// Maps every phandle in the device tree to its node
+fn phandle_map() -> io::Result<HashMap<u32, PathBuf>> {
+    let mut nodes = HashMap::new();
+    let mut stack = vec![PathBuf::from(DEVICETREE_BASE)];
+
+    while let Some(node) = stack.pop() {
+        for entry in fs::read_dir(&node)?.flatten() {
+            let path = entry.path();
+            if entry.file_type().map_or(false, |t| t.is_dir()) {
+                stack.push(path);
+            } else if entry.file_name() == "phandle" {
+                if let Some(&phandle) = read_u32_cells(&path)?.first() {
+                    nodes.insert(phandle, node.clone());
+                }
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+// Device tree properties are stored as big-endian 32-bit cells
+fn read_u32_cells(path: &Path) -> io::Result<Vec<u32>> {
+    Ok(fs::read(path)?
+        .chunks_exact(4)
+        .map(|cell| u32::from_be_bytes([cell[0], cell[1], cell[2], cell[3]]))
+        .collect())
+}
@@ -0,0 +1,396 @@
+//! Records I2C and SPI traffic for debugging purposes.
+//!
+//! A [`Tracer`] can be attached to an [`I2c`] or [`Spi`] instance with
+//! [`I2c::set_tracer`] or [`Spi::set_tracer`]. Once attached, every read, write
+//! and transfer is recorded as a [`TraceRecord`], which contains a timestamp,
+//! the bus and slave address or Slave Select line, the direction, the outgoing
+//! and incoming data, the result and the duration of the operation.
+//!
+//! Records are stored in a ring buffer with a fixed capacity, passed to a
+//! callback, or both. The contents of the ring buffer can be exported as CSV
+//! with [`Tracer::export_csv`], or as a pcap capture with [`Tracer::export_pcap`].
+//!
+//! Tracing is disabled by default. When no `Tracer` is attached, the overhead is
+//! limited to a single check per operation.
+//!
+//! ## CSV format
+//!
+//! The CSV export starts with a header row, followed by one row per record with
+//! the following columns:
+//!
+//! * `timestamp` - Seconds since the Unix epoch, with microsecond precision.
+//! * `duration_us` - Duration of the operation in microseconds.
+//! * `interface` - `I2C` or `SPI`.
+//! * `bus` - Bus ID, or empty if unknown.
+//! * `address` - I2C slave address or SPI Slave Select line in hexadecimal, or empty if unknown.
+//! * `direction` - `Read`, `Write` or `Transfer`.
+//! * `write_data` - Outgoing bytes in hexadecimal, separated by spaces.
+//! * `read_data` - Incoming bytes in hexadecimal, separated by spaces.
+//! * `error` - Error message, or empty if the operation succeeded.
+//!
+//! ## pcap format
+//!
+//! The pcap export uses microsecond timestamps and the `LINKTYPE_USER0` (147) link
+//! type, which can be assigned to a custom dissector in Wireshark or other analyzer
+//! tools. Each packet contains a single record, using the following layout with
+//! all multi-byte values stored in big-endian byte order:
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0 | 1 | Interface (0 = I2C, 1 = SPI) |
+//! | 1 | 1 | Bus ID (0xFF = unknown) |
+//! | 2 | 2 | Slave address or Slave Select line (0xFFFF = unknown) |
+//! | 4 | 1 | Direction (0 = Read, 1 = Write, 2 = Transfer) |
+//! | 5 | 1 | Status (0 = success, 1 = error) |
+//! | 6 | 4 | Duration in microseconds |
+//! | 10 | 4 | Outgoing data length (W) |
+//! | 14 | W | Outgoing data |
+//! | 14 + W | 4 | Incoming data length (R) |
+//! | 18 + W | R | Incoming data |
+//!
+//! [`I2c`]: ../i2c/struct.I2c.html
+//! [`Spi`]: ../spi/struct.Spi.html
+//! [`I2c::set_tracer`]: ../i2c/struct.I2c.html#method.set_tracer
+//! [`Spi::set_tracer`]: ../spi/struct.Spi.html#method.set_tracer
+//! [`Tracer`]: struct.Tracer.html
+//! [`TraceRecord`]: struct.TraceRecord.html
+//! [`Tracer::export_csv`]: struct.Tracer.html#method.export_csv
+//! [`Tracer::export_pcap`]: struct.Tracer.html#method.export_pcap
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// pcap link type reserved for private use
+const LINKTYPE_USER0: u32 = 147;
+const PCAP_SNAPLEN: u32 = 262_144;
+
+/// Peripheral interfaces.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Interface {
+    /// Inter-Integrated Circuit.
+    I2c,
+    /// Serial Peripheral Interface.
+    Spi,
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Interface::I2c => write!(f, "I2C"),
+            Interface::Spi => write!(f, "SPI"),
+        }
+    }
+}
+
+/// Transfer directions.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
+    /// Incoming data only.
+    Read,
+    /// Outgoing data only.
+    Write,
+    /// Outgoing data followed by incoming data (I2C), or both at the same time (SPI).
+    Transfer,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Direction::Read => write!(f, "Read"),
+            Direction::Write => write!(f, "Write"),
+            Direction::Transfer => write!(f, "Transfer"),
+        }
+    }
+}
+
+/// A single recorded operation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceRecord {
+    /// Time at which the operation started.
+    pub timestamp: SystemTime,
+    /// Duration of the operation.
+    pub duration: Duration,
+    /// Peripheral interface.
+    pub interface: Interface,
+    /// Bus ID, if known.
+    pub bus: Option<u8>,
+    /// I2C slave address, or SPI Slave Select line, if known.
+    pub address: Option<u16>,
+    /// Transfer direction.
+    pub direction: Direction,
+    /// Outgoing data.
+    pub write_data: Vec<u8>,
+    /// Incoming data. Empty if the operation failed.
+    pub read_data: Vec<u8>,
+    /// Error message, if the operation failed.
+    pub error: Option<String>,
+}
+
+// The callback is stored in its own mutex, so it can be called without holding
+// the lock that protects the callback slot
+type Callback = Arc<Mutex<Box<dyn FnMut(&TraceRecord) + Send>>>;
+
+/// Records I2C and SPI traffic.
+///
+/// Cloning a `Tracer` creates a new reference to the same ring buffer and
+/// callback, which makes it possible to attach a single `Tracer` to multiple
+/// peripherals, and access its records from any thread.
+///
+/// More information can be found in the [module documentation].
+///
+/// [module documentation]: index.html
+#[derive(Clone)]
+pub struct Tracer {
+    records: Arc<Mutex<VecDeque<TraceRecord>>>,
+    callback: Arc<Mutex<Option<Callback>>>,
+    capacity: usize,
+}
+
+impl Tracer {
+    /// Constructs a new `Tracer` that stores up to `capacity` records in a
+    /// ring buffer.
+    ///
+    /// When the ring buffer is full, the oldest record is discarded.
+    pub fn new(capacity: usize) -> Tracer {
+        Tracer {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            callback: Arc::new(Mutex::new(None)),
+            capacity,
+        }
+    }
+
+    /// Constructs a new `Tracer` that passes every record to `callback`,
+    /// without storing it in a ring buffer.
+    ///
+    /// `callback` is called on the thread that performed the operation, and
+    /// delays the operation's caller until it returns. `callback` can replace
+    /// or remove itself through [`set_callback`], but shouldn't access any
+    /// peripherals this `Tracer` is attached to.
+    ///
+    /// [`set_callback`]: #method.set_callback
+    pub fn with_callback<C>(callback: C) -> Tracer
+    where
+        C: FnMut(&TraceRecord) + Send + 'static,
+    {
+        let tracer = Tracer::new(0);
+        tracer.set_callback(Some(callback));
+
+        tracer
+    }
+
+    /// Returns the maximum number of records stored in the ring buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sets a callback that's called for every record, in addition to storing
+    /// it in the ring buffer.
+    ///
+    /// Set `callback` to `None` to remove a previously set callback.
+    pub fn set_callback<C>(&self, callback: Option<C>)
+    where
+        C: FnMut(&TraceRecord) + Send + 'static,
+    {
+        *lock(&self.callback) = callback.map(|callback| {
+            Arc::new(Mutex::new(
+                Box::new(callback) as Box<dyn FnMut(&TraceRecord) + Send>
+            ))
+        });
+    }
+
+    /// Returns a copy of the records currently stored in the ring buffer,
+    /// starting with the oldest record.
+    pub fn records(&self) -> Vec<TraceRecord> {
+        lock(&self.records).iter().cloned().collect()
+    }
+
+    /// Removes all records from the ring buffer.
+    pub fn clear(&self) {
+        lock(&self.records).clear();
+    }
+
+    /// Writes the records currently stored in the ring buffer to `writer` as CSV.
+    ///
+    /// More information on the format can be found [here].
+    ///
+    /// [here]: index.html#csv-format
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "timestamp,duration_us,interface,bus,address,direction,write_data,read_data,error"
+        )?;
+
+        for record in self.records() {
+            let timestamp = record
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+
+            writeln!(
+                writer,
+                "{}.{:06},{},{},{},{},{},{},{},{}",
+                timestamp.as_secs(),
+                timestamp.subsec_micros(),
+                record.duration.as_micros(),
+                record.interface,
+                record.bus.map_or(String::new(), |bus| bus.to_string()),
+                record
+                    .address
+                    .map_or(String::new(), |address| format!("0x{:02X}", address)),
+                record.direction,
+                hex(&record.write_data),
+                hex(&record.read_data),
+                record.error.as_deref().map_or(String::new(), csv_quote),
+            )?;
+        }
+
+        writer.flush()
+    }
+
+    /// Writes the records currently stored in the ring buffer to `writer` as a
+    /// pcap capture.
+    ///
+    /// More information on the format can be found [here].
+    ///
+    /// [here]: index.html#pcap-format
+    pub fn export_pcap<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        // Global header: magic number, version 2.4, UTC, timestamp accuracy, snapshot length, link type
+        writer.write_all(&0xa1b2_c3d4u32.to_le_bytes())?;
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&4u16.to_le_bytes())?;
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_USER0.to_le_bytes())?;
+
+        for record in self.records() {
+            let payload = pcap_payload(&record);
+            let timestamp = record
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let captured_len = payload.len().min(PCAP_SNAPLEN as usize);
+
+            writer.write_all(&(timestamp.as_secs() as u32).to_le_bytes())?;
+            writer.write_all(&timestamp.subsec_micros().to_le_bytes())?;
+            writer.write_all(&(captured_len as u32).to_le_bytes())?;
+            writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+            writer.write_all(&payload[..captured_len])?;
+        }
+
+        writer.flush()
+    }
+
+    pub(crate) fn record(&self, record: TraceRecord) {
+        // Release the callback slot before calling the callback, so it can call set_callback
+        let callback = lock(&self.callback).clone();
+        if let Some(callback) = callback {
+            (*lock(&callback))(&record);
+        }
+
+        if self.capacity > 0 {
+            let mut records = lock(&self.records);
+            if records.len() == self.capacity {
+                records.pop_front();
+            }
+
+            records.push_back(record);
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("capacity", &self.capacity)
+            .field("len", &lock(&self.records).len())
+            .finish_non_exhaustive()
+    }
+}
+
+// Time at which a traced operation started
+#[derive(Copy, Clone)]
+pub(crate) struct TraceStart {
+    timestamp: SystemTime,
+    instant: Instant,
+}
+
+impl TraceStart {
+    pub(crate) fn now() -> TraceStart {
+        TraceStart {
+            timestamp: SystemTime::now(),
+            instant: Instant::now(),
+        }
+    }
+
+    // Builds a record for an operation that started at self, and has just completed
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn finish(
+        &self,
+        interface: Interface,
+        bus: Option<u8>,
+        address: Option<u16>,
+        direction: Direction,
+        write_data: Vec<u8>,
+        read_data: Vec<u8>,
+        error: Option<String>,
+    ) -> TraceRecord {
+        TraceRecord {
+            timestamp: self.timestamp,
+            duration: self.instant.elapsed(),
+            interface,
+            bus,
+            address,
+            direction,
+            write_data,
+            read_data,
+            error,
+        }
+    }
+}
+
+fn pcap_payload(record: &TraceRecord) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(18 + record.write_data.len() + record.read_data.len());
+
+    payload.push(match record.interface {
+        Interface::I2c => 0,
+        Interface::Spi => 1,
+    });
+    payload.push(record.bus.unwrap_or(0xFF));
+    payload.extend_from_slice(&record.address.unwrap_or(0xFFFF).to_be_bytes());
+    payload.push(match record.direction {
+        Direction::Read => 0,
+        Direction::Write => 1,
+        Direction::Transfer => 2,
+    });
+    payload.push(record.error.is_some() as u8);
+    payload.extend_from_slice(
+        &(record.duration.as_micros().min(u128::from(u32::MAX)) as u32).to_be_bytes(),
+    );
+    payload.extend_from_slice(&(record.write_data.len() as u32).to_be_bytes());
+    payload.extend_from_slice(&record.write_data);
+    payload.extend_from_slice(&(record.read_data.len() as u32).to_be_bytes());
+    payload.extend_from_slice(&record.read_data);
+
+    payload
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn csv_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+// Locks mutex, ignoring poisoning caused by a panic in a callback, so tracing
+// never causes the traced operation to panic
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
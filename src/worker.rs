@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+type Job<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+// Owns a peripheral on a dedicated thread, and runs blocking jobs on it in the
+// order they're submitted.
+pub(crate) struct Worker<T> {
+    sender: Option<Sender<Job<T>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    pub(crate) fn new(name: &str, mut inner: T) -> io::Result<Worker<T>> {
+        let (sender, receiver) = mpsc::channel::<Job<T>>();
+
+        let thread = thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                for job in receiver {
+                    job(&mut inner);
+                }
+            })?;
+
+        Ok(Worker {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    // Submits f to the worker thread. The returned future resolves to None if
+    // the worker stopped before f could complete.
+    pub(crate) fn run<R, F>(&self, f: F) -> Completion<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(State {
+            result: None,
+            closed: false,
+            waker: None,
+        }));
+
+        let completer = Completer {
+            state: state.clone(),
+        };
+
+        if let Some(ref sender) = self.sender {
+            // If the worker thread is gone, dropping the job closes the completion
+            let _ = sender.send(Box::new(move |inner: &mut T| completer.complete(f(inner))));
+        }
+
+        Completion { state }
+    }
+}
+
+impl<T> Drop for Worker<T> {
+    fn drop(&mut self) {
+        // Closing the channel stops the thread once all pending jobs have completed
+        self.sender.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct State<R> {
+    result: Option<R>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+// Stores the result of a job, and wakes the task waiting for it. Dropping a
+// Completer without completing it resolves the Completion to None.
+struct Completer<R> {
+    state: Arc<Mutex<State<R>>>,
+}
+
+impl<R> Completer<R> {
+    fn complete(self, result: R) {
+        self.state.lock().unwrap().result = Some(result);
+    }
+}
+
+impl<R> Drop for Completer<R> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+pub(crate) struct Completion<R> {
+    state: Arc<Mutex<State<R>>>,
+}
+
+impl<R> Future for Completion<R> {
+    type Output = Option<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<R>> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.closed {
+            Poll::Ready(state.result.take())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}